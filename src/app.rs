@@ -1,21 +1,42 @@
-use crate::rest;
-use crate::transaction::Transaction;
+use crate::attack::{Attack, ATTACKS};
+use crate::cli;
+use crate::diagnose::{self, Diagnostic};
+use crate::entropy::Entropy;
+use crate::expect::Verdict;
+use crate::hash;
+use crate::load::{self, LoadConfig, LoadEvent, LoadHandle, SendResult};
+use crate::names;
+use crate::profile::RateProfile;
+use crate::query::{self, Link};
+use crate::rest::{RestClient, RestConfig};
+use crate::scenario::Scenario;
+use crate::session::{Record, Session};
+use crate::stats::RunStats;
+use crate::targets::{Strategy, Targets, STRATEGIES};
+use crate::transaction::{Decoding, SigningScheme, Transaction, DECODINGS, SIGNING_SCHEMES};
+use crate::wire::{self, Format, FORMATS};
+use crate::workload::{self, Job, Mode, Workload};
 use gdk::enums::key;
 use gtk::prelude::*;
 use gtk::*;
-use rand::prelude::*;
 use sourceview::*;
 use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
 use std::{cell::RefCell, rc::Rc};
 
 // ========================================================================== //
 
 const MAX_TX_HISTORY: u32 = 32;
 
+/// Category of the source marks and name of the text tag of diagnostics
+const DIAGNOSTIC: &str = "diagnostic";
+
 // ========================================================================== //
 
 pub enum AppErr {
     GtkErr,
+    RestErr,
 }
 
 // ========================================================================== //
@@ -23,8 +44,16 @@ pub enum AppErr {
 struct AppUI {
     /// Statusbar
     statusbar: Statusbar,
-    /// URL input field
+    /// URL input field, which holds one or more transaction endpoints
     url_input: Entry,
+    /// Strategy for distributing transactions over several endpoints
+    strategy_combo: ComboBoxText,
+    /// Weights of the endpoints for the weighted strategy
+    weights_input: Entry,
+    /// Wire format of the endpoints
+    format_combo: ComboBoxText,
+    /// How strictly JSON transactions are parsed by the client
+    decoding_combo: ComboBoxText,
     /// Transaction list view
     list_view: TreeView,
     /// Transaction list model
@@ -35,6 +64,34 @@ struct AppUI {
     send_btn: Button,
     /// Num input
     num_input: Entry,
+    /// Number of concurrent workers input
+    workers_input: Entry,
+    /// Stop button for the running load
+    stop_btn: Button,
+    /// Target rate input (transactions per second)
+    rate_input: Entry,
+    /// Ramp-up duration input (seconds)
+    ramp_up_input: Entry,
+    /// Hold duration input (seconds)
+    hold_input: Entry,
+    /// Ramp-down duration input (seconds)
+    ramp_down_input: Entry,
+    /// Statistics of the current or last load
+    stats_label: Label,
+    /// Workload selection
+    mode_combo: ComboBoxText,
+    /// Number of bikes input for ownership chains
+    bikes_input: Entry,
+    /// Signing scheme of generated transactions
+    signing_combo: ComboBoxText,
+    /// Seed input for loads, empty for a random seed
+    seed_input: Entry,
+    /// Bike id or transaction hash input of the chain panel
+    chain_input: Entry,
+    /// Chain view
+    chain_view: TreeView,
+    /// Chain model
+    chain_model: ListStore,
 }
 
 pub struct AppData {
@@ -44,6 +101,21 @@ pub struct AppData {
     id: u32,
     /// List of names
     names: Vec<String>,
+    /// Handle to the running load, if any
+    load: Option<LoadHandle>,
+    /// Statistics of the current or last load
+    stats: RunStats,
+    /// Every transaction that was sent, and the secret keys of all outputs
+    /// created by the client
+    session: Session,
+    /// Scenario of the current or last load, if any
+    scenario: Option<Scenario>,
+    /// Client for the REST API of the nodes
+    client: RestClient,
+    /// Source of the names, keys and timestamps of manual transactions
+    entropy: Entropy,
+    /// Chain that was last fetched from a node
+    chain: Vec<Link>,
 }
 
 pub struct App {
@@ -81,34 +153,121 @@ impl App {
         // Create UI elements
         let statusbar = StatusbarBuilder::new().build();
         let url_input = EntryBuilder::new().build();
-        url_input.set_text("http://localhost:8000/transaction");
+        url_input.set_text(cli::DEFAULT_URL);
+        url_input.set_hexpand(true);
+        let strategy_combo = ComboBoxText::new();
+        for strategy in STRATEGIES.iter() {
+            strategy_combo.append(Some(strategy.name()), strategy.description());
+        }
+        strategy_combo.set_active_id(Some(Strategy::default().name()));
+        let weights_input = Entry::new();
+        weights_input.set_width_chars(8);
+        let format_combo = ComboBoxText::new();
+        for format in FORMATS.iter() {
+            format_combo.append(Some(format.name()), format.description());
+        }
+        format_combo.set_active_id(Some(Format::default().name()));
+        let decoding_combo = ComboBoxText::new();
+        for decoding in DECODINGS.iter() {
+            decoding_combo.append(Some(decoding.name()), decoding.description());
+        }
+        decoding_combo.set_active_id(Some(Decoding::default().name()));
         let list_view = TreeViewBuilder::new().headers_visible(true).build();
-        let list_model = ListStore::new(&[u32::static_type(), String::static_type()]);
+        let list_model = ListStore::new(&[
+            u32::static_type(),
+            String::static_type(),
+            String::static_type(),
+        ]);
         list_view.set_model(Some(&list_model));
-        let src_view = build_src_view("json");
+        let src_view = build_src_view("json", &decoding_combo);
         let send_btn = ButtonBuilder::new().label("Send").build();
         let num_input = EntryBuilder::new().build();
+        let workers_input = EntryBuilder::new().build();
+        workers_input.set_text(&load::DEFAULT_WORKERS.to_string());
+        let stop_btn = ButtonBuilder::new().label("Stop").build();
+        stop_btn.set_sensitive(false);
+        let rate_input = build_num_entry("10");
+        let ramp_up_input = build_num_entry("0");
+        let hold_input = build_num_entry("10");
+        let ramp_down_input = build_num_entry("0");
+        let stats_label = Label::new(None);
+        stats_label.set_xalign(0.0);
+        stats_label.set_selectable(true);
+        let mode_combo = ComboBoxText::new();
+        mode_combo.append(Some(&Mode::Register.to_string()), "Register bikes");
+        mode_combo.append(Some(&Mode::Chain.to_string()), "Ownership chains");
+        for attack in ATTACKS.iter() {
+            let text = format!("Attack: {}", attack.description());
+            mode_combo.append(Some(attack.name()), &text);
+        }
+        mode_combo.set_active_id(Some(&Mode::Register.to_string()));
+        let bikes_input = build_num_entry(&workload::DEFAULT_BIKES.to_string());
+        let signing_combo = ComboBoxText::new();
+        for scheme in SIGNING_SCHEMES.iter() {
+            signing_combo.append(Some(scheme.name()), scheme.description());
+        }
+        signing_combo.set_active_id(Some(SigningScheme::default().name()));
+        let seed_input = build_num_entry("");
+        let chain_input = Entry::new();
+        chain_input.set_hexpand(true);
+        let chain_view = TreeViewBuilder::new().headers_visible(true).build();
+        let chain_model = ListStore::new(&[
+            u32::static_type(),
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+            String::static_type(),
+        ]);
+        chain_view.set_model(Some(&chain_model));
         let ui = Rc::new(RefCell::new(AppUI {
             statusbar,
             url_input,
+            strategy_combo,
+            weights_input,
+            format_combo,
+            decoding_combo,
             list_view,
             list_model,
             src_view,
             send_btn,
             num_input,
+            workers_input,
+            stop_btn,
+            rate_input,
+            ramp_up_input,
+            hold_input,
+            ramp_down_input,
+            stats_label,
+            mode_combo,
+            bikes_input,
+            signing_combo,
+            seed_input,
+            chain_input,
+            chain_view,
+            chain_model,
         }));
 
         // Read names
-        let names = std::include_str!("../names.txt")
-            .split("\n")
-            .map(|n| String::from(n.trim()))
-            .collect();
+        let names = names::load();
+
+        // Create REST client
+        let client = match RestClient::new(&RestConfig::default()) {
+            Ok(client) => client,
+            Err(_) => return Err(AppErr::RestErr),
+        };
 
         // Create app and build UI
         let data = Rc::new(RefCell::new(AppData {
             txs: HashMap::new(),
             id: 0,
             names,
+            load: None,
+            stats: RunStats::new(),
+            session: Session::new(),
+            scenario: None,
+            client,
+            entropy: Entropy::default(),
+            chain: Vec::new(),
         }));
         let mut app = App { window, ui, data };
         app.build_ui();
@@ -133,13 +292,18 @@ impl App {
         let pane = PanedBuilder::new().border_width(3).expand(true).build();
         add_tree_column(&self.ui.borrow().list_view, "index", 0);
         add_tree_column(&self.ui.borrow().list_view, "id", 1);
+        add_tree_column(&self.ui.borrow().list_view, "label", 2);
         let wind = ScrolledWindowBuilder::new()
             .hscrollbar_policy(PolicyType::Automatic)
             .vscrollbar_policy(PolicyType::Automatic)
             .expand(true)
             .build();
         wind.add(&self.ui.borrow().list_view);
-        pane.add(&wind);
+        let notebook = Notebook::new();
+        notebook.append_page(&wind, Some(&Label::new(Some("History"))));
+        let chain_area = self.build_chain_area();
+        notebook.append_page(&chain_area, Some(&Label::new(Some("Chain"))));
+        pane.add(&notebook);
         let input_area = self.build_input_area();
         pane.add(&input_area);
         vbox.add(&pane);
@@ -179,6 +343,59 @@ impl App {
         vbox.add(&self.ui.borrow().statusbar);
     }
 
+    fn build_chain_area(&mut self) -> Box {
+        // Chain list
+        add_tree_column(&self.ui.borrow().chain_view, "index", 0);
+        add_tree_column(&self.ui.borrow().chain_view, "timestamp", 1);
+        add_tree_column(&self.ui.borrow().chain_view, "hash", 2);
+        add_tree_column(&self.ui.borrow().chain_view, "link", 3);
+        add_tree_column(&self.ui.borrow().chain_view, "submitted", 4);
+        let wind = ScrolledWindowBuilder::new()
+            .hscrollbar_policy(PolicyType::Automatic)
+            .vscrollbar_policy(PolicyType::Automatic)
+            .expand(true)
+            .build();
+        wind.add(&self.ui.borrow().chain_view);
+        let ui_clone = self.ui.clone();
+        let data_clone = self.data.clone();
+        self.ui
+            .borrow()
+            .chain_view
+            .connect_cursor_changed(move |_| {
+                let selection = ui_clone.borrow().chain_view.get_selection();
+                if let Some((model, iter)) = selection.get_selected() {
+                    let data = data_clone.borrow();
+                    let idx = model.get_value(&iter, 0).get::<u32>().unwrap();
+                    let link = &data.chain[idx as usize];
+                    let buffer = ui_clone.borrow_mut().src_view.get_buffer().unwrap();
+                    buffer.set_text(&link.tx.to_json());
+                }
+            });
+
+        // Buttons
+        let hbox = Box::new(Orientation::Horizontal, 0);
+        let chain_btn = ButtonBuilder::new().label("Fetch Chain").build();
+        let ui_clone = self.ui.clone();
+        let data_clone = self.data.clone();
+        chain_btn.connect_clicked(move |_| {
+            app_fetch_chain(&mut data_clone.borrow_mut(), &mut ui_clone.borrow_mut());
+        });
+        let tx_btn = ButtonBuilder::new().label("Fetch Transaction").build();
+        let ui_clone = self.ui.clone();
+        let data_clone = self.data.clone();
+        tx_btn.connect_clicked(move |_| {
+            app_fetch_transaction(&data_clone.borrow(), &mut ui_clone.borrow_mut());
+        });
+        hbox.add(&self.ui.borrow().chain_input);
+        hbox.add(&chain_btn);
+        hbox.add(&tx_btn);
+
+        let vbox = Box::new(Orientation::Vertical, 0);
+        vbox.add(&hbox);
+        vbox.add(&wind);
+        vbox
+    }
+
     fn build_input_area(&mut self) -> Box {
         // Source field
         let wind = ScrolledWindowBuilder::new()
@@ -200,34 +417,83 @@ impl App {
         let data_clone = self.data.clone();
         help_btn.connect_clicked(move |_| {
             let num = ui_clone.borrow().num_input.get_text().unwrap();
-            match num.parse::<u32>() {
-                Ok(num) => {
-                    for _ in 0..num {
-                        app_set_new_transaction(
-                            &mut data_clone.borrow_mut(),
-                            &mut ui_clone.borrow_mut(),
-                        );
-                        app_send_transaction(
-                            &mut data_clone.borrow_mut(),
-                            &mut ui_clone.borrow_mut(),
-                        );
-                    }
+            let num = match num.parse::<u32>() {
+                Ok(num) => num,
+                Err(e) => {
+                    app_push_statusbar(
+                        &mut ui_clone.borrow_mut(),
+                        "error",
+                        &format!("Invalid number for 'Send N' (text: {}, error: {})", num, e),
+                    );
+                    return;
                 }
-                Err(e) => app_push_statusbar(
-                    &mut ui_clone.borrow_mut(),
-                    "error",
-                    &format!("Invalid number for 'Send N' (text: {}, error: {})", num, e),
-                ),
+            };
+            let workers = ui_clone.borrow().workers_input.get_text().unwrap();
+            let workers = app_parse_input(&mut ui_clone.borrow_mut(), &workers, "workers");
+            if let Some(workers) = workers {
+                app_start_load(&data_clone, &ui_clone, num, workers, None);
+            }
+        });
+        let rate_btn = ButtonBuilder::new().label("Send Rate").build();
+        let ui_clone = self.ui.clone();
+        let data_clone = self.data.clone();
+        rate_btn.connect_clicked(move |_| {
+            let workers = ui_clone.borrow().workers_input.get_text().unwrap();
+            let workers = app_parse_input(&mut ui_clone.borrow_mut(), &workers, "workers");
+            let profile = app_read_profile(&mut ui_clone.borrow_mut());
+            if let (Some(workers), Some(profile)) = (workers, profile) {
+                let count = profile.total_count().min(u32::max_value() as u64) as u32;
+                app_start_load(&data_clone, &ui_clone, count, workers, Some(profile));
+            }
+        });
+        let ui_clone = self.ui.clone();
+        let data_clone = self.data.clone();
+        self.ui.borrow().stop_btn.connect_clicked(move |_| {
+            if let Some(ref load) = data_clone.borrow().load {
+                load.stop();
+                app_push_statusbar(&mut ui_clone.borrow_mut(), "info", "Stopping load");
             }
         });
         hbox.add(&self.ui.borrow().send_btn);
         hbox.add(&help_btn);
         hbox.add(&self.ui.borrow().num_input);
+        hbox.add(&Label::new(Some("Workers")));
+        hbox.add(&self.ui.borrow().workers_input);
+        hbox.add(&self.ui.borrow().stop_btn);
+        hbox.add(&self.ui.borrow().mode_combo);
+        hbox.add(&Label::new(Some("Bikes")));
+        hbox.add(&self.ui.borrow().bikes_input);
+        hbox.add(&self.ui.borrow().signing_combo);
+        hbox.add(&Label::new(Some("Seed")));
+        hbox.add(&self.ui.borrow().seed_input);
+
+        // Targets
+        let target_box = Box::new(Orientation::Horizontal, 0);
+        target_box.add(&self.ui.borrow().url_input);
+        target_box.add(&self.ui.borrow().strategy_combo);
+        target_box.add(&Label::new(Some("Weights")));
+        target_box.add(&self.ui.borrow().weights_input);
+        target_box.add(&self.ui.borrow().format_combo);
+        target_box.add(&self.ui.borrow().decoding_combo);
+
+        // Rate profile
+        let rate_box = Box::new(Orientation::Horizontal, 0);
+        rate_box.add(&rate_btn);
+        rate_box.add(&Label::new(Some("Rate (tx/s)")));
+        rate_box.add(&self.ui.borrow().rate_input);
+        rate_box.add(&Label::new(Some("Ramp up (s)")));
+        rate_box.add(&self.ui.borrow().ramp_up_input);
+        rate_box.add(&Label::new(Some("Hold (s)")));
+        rate_box.add(&self.ui.borrow().hold_input);
+        rate_box.add(&Label::new(Some("Ramp down (s)")));
+        rate_box.add(&self.ui.borrow().ramp_down_input);
 
         let vbox = Box::new(Orientation::Vertical, 0);
         vbox.add(&wind);
-        vbox.add(&self.ui.borrow().url_input);
+        vbox.add(&target_box);
         vbox.add(&hbox);
+        vbox.add(&rate_box);
+        vbox.add(&self.ui.borrow().stats_label);
         vbox
     }
 
@@ -240,6 +506,36 @@ impl App {
         let menu_file = Menu::new();
         menu_file_item.set_submenu(Some(&menu_file));
 
+        // FILE - Open scenario
+        let file_scenario = MenuItem::new_with_label("Open Scenario...");
+        let window = self.window.clone();
+        let ui_clone = self.ui.clone();
+        let data_clone = self.data.clone();
+        file_scenario.connect_activate(move |_| {
+            app_open_scenario(&window, &data_clone, &ui_clone);
+        });
+        menu_file.append(&file_scenario);
+
+        // FILE - Open session
+        let file_open_session = MenuItem::new_with_label("Open Session...");
+        let window = self.window.clone();
+        let ui_clone = self.ui.clone();
+        let data_clone = self.data.clone();
+        file_open_session.connect_activate(move |_| {
+            app_open_session(&window, &data_clone, &ui_clone);
+        });
+        menu_file.append(&file_open_session);
+
+        // FILE - Save session
+        let file_save_session = MenuItem::new_with_label("Save Session...");
+        let window = self.window.clone();
+        let ui_clone = self.ui.clone();
+        let data_clone = self.data.clone();
+        file_save_session.connect_activate(move |_| {
+            app_save_session(&window, &data_clone.borrow(), &mut ui_clone.borrow_mut());
+        });
+        menu_file.append(&file_save_session);
+
         // FILE - Quit
         let file_quit = MenuItem::new_with_label("Quit");
         file_quit.connect_activate(|_| {
@@ -263,6 +559,25 @@ impl App {
         });
         sim_menu.append(&sim_quit_btn);
 
+        // SIM - Attacks
+        let sim_attacks_item = MenuItem::new_with_label("Attacks");
+        let sim_attacks = Menu::new();
+        sim_attacks_item.set_submenu(Some(&sim_attacks));
+        for attack in ATTACKS.iter().cloned() {
+            let item = MenuItem::new_with_label(attack.description());
+            let ui_clone = self.ui.clone();
+            let data_clone = self.data.clone();
+            item.connect_activate(move |_| {
+                app_generate_attack(
+                    &mut data_clone.borrow_mut(),
+                    &mut ui_clone.borrow_mut(),
+                    attack,
+                );
+            });
+            sim_attacks.append(&item);
+        }
+        sim_menu.append(&sim_attacks_item);
+
         bar
     }
 }
@@ -270,7 +585,7 @@ impl App {
 // ========================================================================== //
 
 /// Build a source view for the language with the specified identifier
-fn build_src_view(lang: &str) -> View {
+fn build_src_view(lang: &str, decoding_combo: &ComboBoxText) -> View {
     let lang_mgr = LanguageManager::new();
     let lang = lang_mgr
         .get_language(lang)
@@ -278,9 +593,82 @@ fn build_src_view(lang: &str) -> View {
     let buf = Buffer::new_with_language(&lang);
     let view = View::new_with_buffer(&buf);
     view.set_show_line_numbers(true);
+    build_diagnostics(&view, &buf, decoding_combo);
     view
 }
 
+/// Validate the transaction in a source view as the user types, with the
+/// decoding mode of a combo box. A problem is underlined and marked in the
+/// gutter, and hovering either shows its message.
+fn build_diagnostics(view: &View, buf: &Buffer, decoding_combo: &ComboBoxText) {
+    let current: Rc<RefCell<Option<Diagnostic>>> = Rc::new(RefCell::new(None));
+    let tag = buf
+        .create_tag(Some(DIAGNOSTIC), &[("underline", &pango::Underline::Error)])
+        .expect("Failed to create diagnostic tag");
+
+    let attributes = MarkAttributes::new();
+    attributes.set_icon_name("dialog-error");
+    let current_clone = current.clone();
+    attributes.connect_query_tooltip_text(move |_, _| {
+        let msg = current_clone.borrow().as_ref().map(|d| d.message.clone());
+        msg.unwrap_or_default().into()
+    });
+    view.set_mark_attributes(DIAGNOSTIC, &attributes, 0);
+    view.set_show_line_marks(true);
+
+    let current_clone = current.clone();
+    let combo = decoding_combo.clone();
+    let check = Rc::new(move |buf: &Buffer| {
+        let (start, end) = buf.get_bounds();
+        buf.remove_tag(&tag, &start, &end);
+        buf.remove_source_marks(&start, &end, Some(DIAGNOSTIC));
+        let text = buf.get_text(&start, &end, true).unwrap();
+        let decoding = combo
+            .get_active_id()
+            .and_then(|s| s.parse::<Decoding>().ok())
+            .unwrap_or_default();
+        let diagnostic = diagnose::diagnose(&text, decoding);
+        if let Some(ref d) = diagnostic {
+            let from = buf.get_iter_at_offset(d.start as i32);
+            let to = buf.get_iter_at_offset(d.end as i32);
+            buf.apply_tag(&tag, &from, &to);
+            buf.create_source_mark(None, DIAGNOSTIC, &from);
+        }
+        *current_clone.borrow_mut() = diagnostic;
+    });
+    let check_clone = check.clone();
+    buf.connect_changed(move |buf| check_clone(buf));
+    // Check the text again with the new mode
+    let buf_clone = buf.clone();
+    decoding_combo.connect_changed(move |_| check(&buf_clone));
+
+    view.set_has_tooltip(true);
+    view.connect_query_tooltip(move |view, x, y, _, tooltip| {
+        let (x, y) = view.window_to_buffer_coords(TextWindowType::Widget, x, y);
+        let offset = match view.get_iter_at_location(x, y) {
+            Some(iter) => iter.get_offset() as usize,
+            None => return false,
+        };
+        match *current.borrow() {
+            Some(ref d) if d.start <= offset && offset < d.end => {
+                tooltip.set_text(Some(&d.message));
+                true
+            }
+            _ => false,
+        }
+    });
+}
+
+// ========================================================================== //
+
+/// Build a small entry for numeric input with an initial value
+fn build_num_entry(text: &str) -> Entry {
+    let entry = Entry::new();
+    entry.set_width_chars(6);
+    entry.set_text(text);
+    entry
+}
+
 // ========================================================================== //
 
 /// Add a column to a tree view
@@ -304,54 +692,628 @@ fn app_push_statusbar(ui: &mut AppUI, id: &str, msg: &str) {
 // ========================================================================== //
 
 /// Generate a random name
-fn app_gen_rand_name(data: &AppData) -> String {
-    let mut rng = rand::thread_rng();
-    let name_idx = rng.gen_range(0, data.names.len());
-    let rand_idx = rng.gen_range(0, 1000);
-    format!("{}_{}", data.names[name_idx], rand_idx)
+fn app_gen_rand_name(data: &mut AppData) -> String {
+    data.entropy.gen_name(&data.names)
 }
 
 // ========================================================================== //
 
-/// Send the transaction that is currently in the input area
+/// Send the transaction that is currently in the input area to the
+/// endpoints that the strategy picks for it
 fn app_send_transaction(data: &mut AppData, ui: &mut AppUI) {
-    let url = ui.url_input.get_text().unwrap();
+    let targets = match app_read_targets(ui) {
+        Some(targets) => targets,
+        None => return,
+    };
     let buffer = ui.src_view.get_buffer().unwrap();
     let json = buffer
         .get_text(&buffer.get_start_iter(), &buffer.get_end_iter(), true)
         .unwrap();
-    match Transaction::from_json(&json) {
+    let decoding = ui
+        .decoding_combo
+        .get_active_id()
+        .and_then(|s| s.parse::<Decoding>().ok())
+        .unwrap_or_default();
+    match Transaction::from_json_with(&json, decoding) {
         Ok(tx) => {
-            app_add_transaction(data, ui, tx);
-            match rest::post(&url, &json) {
-                Ok((r, s)) => app_push_statusbar(
-                    ui,
-                    "info",
-                    &format!("Successfully sent transaction ({}, code {})", r, s),
-                ),
-                Err(e) => {
-                    app_push_statusbar(ui, "error", &format!("Failed to send transaction ({})", e))
+            let mut job = Job::new(workload::LABEL_MANUAL, tx.clone(), None);
+            job.expected = Verdict::of_content(&tx);
+            let picks: Vec<(String, Format)> = targets
+                .pick(data.id as usize, tx.get_id())
+                .into_iter()
+                .map(|t| (String::from(t.url), t.format))
+                .collect();
+            let bodies: Vec<Vec<u8>> = picks
+                .iter()
+                .map(|(_, format)| match format {
+                    Format::Json => json.clone().into_bytes(),
+                    Format::Binary => wire::encode(&tx, *format),
+                })
+                .collect();
+            app_add_transaction(data, ui, tx, workload::LABEL_MANUAL);
+            for ((url, format), body) in picks.into_iter().zip(bodies) {
+                let sent_at = Instant::now();
+                let response = data.client.post(&url, &body, format.content_type());
+                let mut result = SendResult {
+                    job: job.clone(),
+                    url,
+                    sent_at,
+                    response,
+                    latency: sent_at.elapsed(),
+                    lag: Duration::from_secs(0),
+                    mismatch: None,
+                };
+                result.check_expected();
+                data.session.records.push(Record::from_result(&result));
+                let (url, ms) = (&result.url, result.latency.as_secs_f64() * 1000.0);
+                if let Some(ref m) = result.mismatch {
+                    app_push_statusbar(
+                        ui,
+                        "error",
+                        &format!("Unexpected verdict from {} ({})", url, m),
+                    );
+                    continue;
+                }
+                match result.response {
+                    Ok((ref r, s)) => app_push_statusbar(
+                        ui,
+                        "info",
+                        &format!(
+                            "Successfully sent transaction to {} ({}, code {}, {:.1} ms)",
+                            url, r, s, ms
+                        ),
+                    ),
+                    Err(ref e) => app_push_statusbar(
+                        ui,
+                        "error",
+                        &format!(
+                            "Failed to send transaction to {} ({}, {:.1} ms)",
+                            url, e, ms
+                        ),
+                    ),
                 }
             }
         }
-        Err(e) => app_push_statusbar(ui, "error", &format!("Invalid input ({})", e)),
+        Err(e) => {
+            let msg = match diagnose::diagnose(&json, decoding) {
+                Some(d) => d.message,
+                None => e.to_string(),
+            };
+            app_push_statusbar(ui, "error", &format!("Invalid input ({})", msg))
+        }
     }
 }
 
 // ========================================================================== //
 
-/// Generate a new register transaction and set it for the input area
+/// Fetch the chain of the bike in the chain input from the first node that
+/// its transactions are sent to. Each link is checked, and the chain is
+/// compared to the transactions of the bike that the client submitted.
+fn app_fetch_chain(data: &mut AppData, ui: &mut AppUI) {
+    let id = String::from(ui.chain_input.get_text().unwrap().trim());
+    if id.is_empty() {
+        app_push_statusbar(ui, "error", "Missing bike id");
+        return;
+    }
+    let url = match app_read_targets(ui) {
+        Some(targets) => String::from(targets.pick(0, &id)[0].url),
+        None => return,
+    };
+    let chain = match query::fetch_chain(&data.client, &url, &id) {
+        Ok(chain) => chain,
+        Err(e) => {
+            app_push_statusbar(ui, "error", &format!("Failed to fetch chain ({})", e));
+            return;
+        }
+    };
+    let submitted = data.session.accepted(&id);
+    let divergence = query::divergence(&chain, &submitted);
+    data.chain = query::check_chain(chain, &submitted);
+
+    ui.chain_model.clear();
+    for (i, link) in data.chain.iter().enumerate() {
+        let timestamp = link.tx.get_timestamp().to_string();
+        let hash = hash::hash_to_str(&link.report.hash);
+        let follows = link.report.summary();
+        let submitted = if link.submitted { "yes" } else { "no" };
+        ui.chain_model.insert_with_values(
+            None,
+            &[0, 1, 2, 3, 4],
+            &[&(i as u32), &timestamp, &hash, &follows, &submitted],
+        );
+    }
+
+    let broken = data.chain.iter().filter(|l| !l.report.is_valid()).count();
+    let mut msg = format!(
+        "Chain of '{}' on {} has {} transactions and {} broken links",
+        id,
+        url,
+        data.chain.len(),
+        broken
+    );
+    if let Some(i) = divergence {
+        msg.push_str(&format!(
+            ", differs from the {} submitted transactions at {}",
+            submitted.len(),
+            i
+        ));
+    }
+    let context = if broken > 0 || divergence.is_some() {
+        "error"
+    } else {
+        "info"
+    };
+    app_push_statusbar(ui, context, &msg);
+}
+
+/// Fetch the transaction with the hash in the chain input from the first
+/// node, and show it in the input area
+fn app_fetch_transaction(data: &AppData, ui: &mut AppUI) {
+    let text = ui.chain_input.get_text().unwrap();
+    let hash = match hash::str_to_hash(text.trim()) {
+        Some(hash) => hash,
+        None => {
+            app_push_statusbar(
+                ui,
+                "error",
+                &format!("Invalid transaction hash (text: {})", text),
+            );
+            return;
+        }
+    };
+    let url = match app_read_targets(ui) {
+        Some(targets) => String::from(targets.pick(0, "")[0].url),
+        None => return,
+    };
+    match query::fetch_transaction(&data.client, &url, &hash) {
+        Ok(Some(tx)) => {
+            let buffer = ui.src_view.get_buffer().unwrap();
+            buffer.set_text(&tx.to_json());
+            app_push_statusbar(ui, "info", &format!("Fetched transaction from {}", url));
+        }
+        Ok(None) => app_push_statusbar(
+            ui,
+            "error",
+            &format!("Node {} does not have the transaction", url),
+        ),
+        Err(e) => app_push_statusbar(ui, "error", &format!("Failed to fetch transaction ({})", e)),
+    }
+}
+
+// ========================================================================== //
+
+/// Parse the text of an input field. Pushes an error to the statusbar and
+/// returns None if the text is not valid.
+fn app_parse_input<T: FromStr>(ui: &mut AppUI, text: &str, what: &str) -> Option<T> {
+    match text.trim().parse::<T>() {
+        Ok(v) => Some(v),
+        Err(_) => {
+            app_push_statusbar(
+                ui,
+                "error",
+                &format!("Invalid value for {} (text: {})", what, text),
+            );
+            None
+        }
+    }
+}
+
+// ========================================================================== //
+
+/// Read the rate profile from the input fields
+fn app_read_profile(ui: &mut AppUI) -> Option<RateProfile> {
+    let rate = ui.rate_input.get_text().unwrap();
+    let ramp_up = ui.ramp_up_input.get_text().unwrap();
+    let hold = ui.hold_input.get_text().unwrap();
+    let ramp_down = ui.ramp_down_input.get_text().unwrap();
+    let rate: f64 = app_parse_input(ui, &rate, "rate")?;
+    let ramp_up: f64 = app_parse_input(ui, &ramp_up, "ramp up")?;
+    let hold: f64 = app_parse_input(ui, &hold, "hold")?;
+    let ramp_down: f64 = app_parse_input(ui, &ramp_down, "ramp down")?;
+    if !(rate > 0.0) || ramp_up < 0.0 || hold < 0.0 || ramp_down < 0.0 {
+        app_push_statusbar(ui, "error", "Rate and durations must be positive");
+        return None;
+    }
+    Some(RateProfile {
+        rate,
+        ramp_up: Duration::from_secs_f64(ramp_up),
+        hold: Duration::from_secs_f64(hold),
+        ramp_down: Duration::from_secs_f64(ramp_down),
+    })
+}
+
+// ========================================================================== //
+
+/// Read the transaction endpoints, the strategy, the weights and the wire
+/// format from the input fields
+fn app_read_targets(ui: &mut AppUI) -> Option<Targets> {
+    let urls = ui.url_input.get_text().unwrap();
+    let weights = ui.weights_input.get_text().unwrap();
+    let strategy = ui
+        .strategy_combo
+        .get_active_id()
+        .and_then(|s| s.parse::<Strategy>().ok())
+        .unwrap_or_default();
+    let format = ui
+        .format_combo
+        .get_active_id()
+        .and_then(|s| s.parse::<Format>().ok())
+        .unwrap_or_default();
+    let targets = Targets::parse(&urls, &weights, strategy)
+        .and_then(|targets| targets.with_formats(Some(vec![format])));
+    match targets {
+        Ok(targets) => Some(targets),
+        Err(e) => {
+            app_push_statusbar(ui, "error", &format!("Invalid targets ({})", e));
+            None
+        }
+    }
+}
+
+// ========================================================================== //
+
+/// Start sending 'count' transactions of the selected workload using a number
+/// of concurrent workers. If a rate profile is specified, the transactions are
+/// sent on its schedule.
+fn app_start_load(
+    data: &Rc<RefCell<AppData>>,
+    ui: &Rc<RefCell<AppUI>>,
+    count: u32,
+    workers: usize,
+    profile: Option<RateProfile>,
+) {
+    if workers == 0 {
+        app_push_statusbar(
+            &mut ui.borrow_mut(),
+            "error",
+            "At least one worker is needed",
+        );
+        return;
+    }
+
+    let targets = match app_read_targets(&mut ui.borrow_mut()) {
+        Some(targets) => targets,
+        None => return,
+    };
+    let decoding = ui.borrow().decoding_combo.get_active_id();
+    let decoding = decoding
+        .and_then(|s| s.parse::<Decoding>().ok())
+        .unwrap_or_default();
+    let seed = ui.borrow().seed_input.get_text().unwrap();
+    let seed = match seed.trim() {
+        "" => None,
+        seed => match app_parse_input(&mut ui.borrow_mut(), seed, "seed") {
+            Some(seed) => Some(seed),
+            None => return,
+        },
+    };
+    let config = LoadConfig {
+        targets: targets.with_seed(seed),
+        client: data.borrow().client.clone(),
+        workers,
+        profile,
+        duration: None,
+        decoding,
+    };
+    let mode = ui.borrow().mode_combo.get_active_id();
+    let mode = mode
+        .and_then(|m| m.parse::<Mode>().ok())
+        .unwrap_or(Mode::Register);
+    let bikes = ui.borrow().bikes_input.get_text().unwrap();
+    let bikes = match app_parse_input(&mut ui.borrow_mut(), &bikes, "bikes") {
+        Some(bikes) => bikes,
+        None => return,
+    };
+    let signing = ui.borrow().signing_combo.get_active_id();
+    let signing = signing
+        .and_then(|s| s.parse::<SigningScheme>().ok())
+        .unwrap_or_default();
+    let names = data.borrow().names.clone();
+    let capacity = names::capacity(&names);
+    if bikes as usize > capacity {
+        let msg = format!("Number of bikes must be at most {}", capacity);
+        app_push_statusbar(&mut ui.borrow_mut(), "error", &msg);
+        return;
+    }
+    let entropy = Entropy::new(seed, None);
+    let workload = mode.make_workload(names, count, bikes, signing, entropy);
+    app_run_load(data, ui, config, workload, None);
+}
+
+// ========================================================================== //
+
+/// Let the user pick a JSON file to open or to save to. 'what' names the kind
+/// of file in the filter. Returns None if the user cancelled.
+fn app_choose_json_file(
+    window: &Window,
+    title: &str,
+    action: FileChooserAction,
+    what: &str,
+) -> Option<String> {
+    let accept = match action {
+        FileChooserAction::Save => "_Save",
+        _ => "_Open",
+    };
+    let dialog = FileChooserDialog::with_buttons(
+        Some(title),
+        Some(window),
+        action,
+        &[
+            ("_Cancel", ResponseType::Cancel),
+            (accept, ResponseType::Accept),
+        ],
+    );
+    dialog.set_do_overwrite_confirmation(true);
+    let filter = FileFilter::new();
+    filter.set_name(Some(&format!("{} (*.json)", what)));
+    filter.add_pattern("*.json");
+    dialog.add_filter(&filter);
+    let response = dialog.run();
+    let path = dialog.get_filename();
+    dialog.destroy();
+    if response != ResponseType::Accept.into() {
+        return None;
+    }
+    path.map(|path| path.to_string_lossy().into_owned())
+}
+
+/// Let the user pick a scenario file and run it
+fn app_open_scenario(window: &Window, data: &Rc<RefCell<AppData>>, ui: &Rc<RefCell<AppUI>>) {
+    let path = match app_choose_json_file(
+        window,
+        "Open Scenario",
+        FileChooserAction::Open,
+        "Scenarios",
+    ) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let scenario = Scenario::load(&path);
+    match scenario.and_then(|s| s.load_config().map(|config| (s, config))) {
+        Ok((scenario, config)) => {
+            let workload = scenario.workload(data.borrow().names.clone());
+            app_run_load(data, ui, config, workload, Some(scenario));
+        }
+        Err(e) => app_push_statusbar(&mut ui.borrow_mut(), "error", &e),
+    }
+}
+
+// ========================================================================== //
+
+/// Let the user pick a file and save the session to it
+fn app_save_session(window: &Window, data: &AppData, ui: &mut AppUI) {
+    let path =
+        match app_choose_json_file(window, "Save Session", FileChooserAction::Save, "Sessions") {
+            Some(path) => path,
+            None => return,
+        };
+    match data.session.save(&path) {
+        Ok(_) => app_push_statusbar(
+            ui,
+            "info",
+            &format!(
+                "Saved {} transactions and {} keys to {}",
+                data.session.records.len(),
+                data.session.wallet.len(),
+                path
+            ),
+        ),
+        Err(e) => app_push_statusbar(ui, "error", &e),
+    }
+}
+
+/// Let the user pick a session file and replace the current session with it.
+/// The newest transactions of the session are shown in the history.
+fn app_open_session(window: &Window, data: &Rc<RefCell<AppData>>, ui: &Rc<RefCell<AppUI>>) {
+    if data.borrow().load.is_some() {
+        app_push_statusbar(&mut ui.borrow_mut(), "error", "A load is already running");
+        return;
+    }
+    let path =
+        match app_choose_json_file(window, "Open Session", FileChooserAction::Open, "Sessions") {
+            Some(path) => path,
+            None => return,
+        };
+
+    let mut data = data.borrow_mut();
+    let mut ui = ui.borrow_mut();
+    let session = match Session::load(&path) {
+        Ok(session) => session,
+        Err(e) => {
+            app_push_statusbar(&mut ui, "error", &e);
+            return;
+        }
+    };
+    ui.list_model.clear();
+    data.txs.clear();
+    let shown = session
+        .records
+        .len()
+        .saturating_sub(MAX_TX_HISTORY as usize);
+    for record in &session.records[shown..] {
+        app_add_transaction(&mut data, &ui, record.transaction.clone(), &record.label);
+    }
+    let msg = format!(
+        "Opened {} transactions and {} keys from {}",
+        session.records.len(),
+        session.wallet.len(),
+        path
+    );
+    data.session = session;
+    app_push_statusbar(&mut ui, "info", &msg);
+}
+
+// ========================================================================== //
+
+/// Start a load. Results are polled from the main loop and added to the
+/// history. If the load runs a scenario, its assertions are checked when the
+/// load has finished.
+fn app_run_load(
+    data: &Rc<RefCell<AppData>>,
+    ui: &Rc<RefCell<AppUI>>,
+    config: LoadConfig,
+    workload: std::boxed::Box<dyn Workload>,
+    scenario: Option<Scenario>,
+) {
+    if data.borrow().load.is_some() {
+        app_push_statusbar(&mut ui.borrow_mut(), "error", "A load is already running");
+        return;
+    }
+
+    let msg = match scenario {
+        Some(ref s) => format!(
+            "Running scenario '{}' with {} workers",
+            s.name, config.workers
+        ),
+        None => format!("Running load with {} workers", config.workers),
+    };
+    {
+        let ui = ui.borrow();
+        let targets = &config.targets;
+        let weights: Vec<String> = targets.weights().iter().map(|w| w.to_string()).collect();
+        ui.url_input.set_text(&targets.to_string());
+        ui.strategy_combo
+            .set_active_id(Some(targets.strategy().name()));
+        ui.weights_input.set_text(&weights.join(", "));
+        ui.format_combo
+            .set_active_id(Some(targets.formats()[0].name()));
+        ui.decoding_combo
+            .set_active_id(Some(config.decoding.name()));
+    }
+    data.borrow_mut().load = Some(load::start(config, workload));
+    data.borrow_mut().stats = RunStats::new();
+    data.borrow_mut().scenario = scenario;
+    ui.borrow().stop_btn.set_sensitive(true);
+    app_push_statusbar(&mut ui.borrow_mut(), "info", &msg);
+
+    let data = data.clone();
+    let ui = ui.clone();
+    gtk::timeout_add(50, move || {
+        let mut data = data.borrow_mut();
+        let mut ui = ui.borrow_mut();
+        loop {
+            let event = match data.load {
+                Some(ref load) => load.try_recv(),
+                None => return Continue(false),
+            };
+            match event {
+                Some(LoadEvent::Sent(result)) => app_on_sent(&mut data, &mut ui, result),
+                Some(LoadEvent::Done) => {
+                    data.load = None;
+                    data.stats.finish();
+                    let failures = match data.scenario {
+                        Some(ref scenario) => scenario.check(&data.stats),
+                        None if data.stats.mismatches > 0 => {
+                            vec![format!("unexpected verdicts {} > 0", data.stats.mismatches)]
+                        }
+                        None => Vec::new(),
+                    };
+                    let mut text = data.stats.to_string();
+                    for failure in &failures {
+                        text.push_str(&format!("\nassertion failed: {}", failure));
+                    }
+                    ui.stats_label.set_text(&text);
+                    ui.stop_btn.set_sensitive(false);
+                    match failures.len() {
+                        0 => app_push_statusbar(&mut ui, "info", "Load finished"),
+                        n => app_push_statusbar(
+                            &mut ui,
+                            "error",
+                            &format!("Load finished, {} assertions failed", n),
+                        ),
+                    }
+                    return Continue(false);
+                }
+                None => {
+                    ui.stats_label.set_text(&data.stats.to_string());
+                    return Continue(true);
+                }
+            }
+        }
+    });
+}
+
+// ========================================================================== //
+
+/// Handle the result of a transaction that was sent by the load engine
+fn app_on_sent(data: &mut AppData, ui: &mut AppUI, result: SendResult) {
+    data.stats.record(&result);
+    data.session.records.push(Record::from_result(&result));
+    let ms = result.latency.as_secs_f64() * 1000.0;
+    let msg = match result.response {
+        Ok((ref r, s)) => format!(
+            "Successfully sent transaction ({}, code {}, {:.1} ms)",
+            r, s, ms
+        ),
+        Err(ref e) => format!("Failed to send transaction ({}, {:.1} ms)", e, ms),
+    };
+    let mismatch = result.mismatch;
+    let job = result.job;
+    if let Some(sk) = job.secret_key {
+        data.session.wallet.insert(&job.tx, sk);
+    }
+    app_add_transaction(data, ui, job.tx, &job.label);
+    match mismatch {
+        Some(m) => app_push_statusbar(ui, "error", &format!("Unexpected verdict ({})", m)),
+        None => app_push_statusbar(ui, "info", &msg),
+    }
+}
+
+// ========================================================================== //
+
+/// Generate a new register transaction, signed with the selected scheme, and
+/// set it for the input area
 fn app_set_new_transaction(data: &mut AppData, ui: &mut AppUI) {
-    let name = app_gen_rand_name(&data);
-    let (tx, _) = Transaction::debug_make_register(name);
+    let name = app_gen_rand_name(data);
+    let scheme = ui
+        .signing_combo
+        .get_active_id()
+        .and_then(|s| s.parse::<SigningScheme>().ok())
+        .unwrap_or_default();
+    let (tx, sk) = Transaction::debug_make_register_with(name, scheme, &mut data.entropy);
+    data.session.wallet.insert(&tx, sk);
     let buffer = ui.src_view.get_buffer().unwrap();
     buffer.set_text(&tx.to_json());
 }
 
+/// Generate the transactions of an attack on a new bike, signed with the
+/// selected scheme. Each is added to the history with its label, and the last
+/// one, which the node must reject, is set for the input area.
+fn app_generate_attack(data: &mut AppData, ui: &mut AppUI, attack: Attack) {
+    let name = app_gen_rand_name(data);
+    let scheme = ui
+        .signing_combo
+        .get_active_id()
+        .and_then(|s| s.parse::<SigningScheme>().ok())
+        .unwrap_or_default();
+    let jobs = attack.make_jobs(name, scheme, &mut data.entropy);
+    let count = jobs.len();
+    let mut body = String::new();
+    for job in jobs {
+        if let Some(sk) = job.secret_key {
+            data.session.wallet.insert(&job.tx, sk);
+        }
+        body = job.body.unwrap_or_else(|| job.tx.to_json());
+        app_add_transaction(data, ui, job.tx, &job.label);
+    }
+    let buffer = ui.src_view.get_buffer().unwrap();
+    buffer.set_text(&body);
+    app_push_statusbar(
+        ui,
+        "info",
+        &format!(
+            "Generated attack '{}' ({} transactions added to the history)",
+            attack.name(),
+            count
+        ),
+    );
+}
+
 // ========================================================================== //
 
-/// Add a transaction to the history
-fn app_add_transaction(data: &mut AppData, ui: &AppUI, tx: Transaction) {
+/// Add a transaction to the history, labeled with the kind of job that sent it
+fn app_add_transaction(data: &mut AppData, ui: &AppUI, tx: Transaction, label: &str) {
     // Remove the oldest if the limit is reached
     if data.txs.len() as u32 >= MAX_TX_HISTORY {
         match ui.list_model.get_iter_first() {
@@ -368,6 +1330,6 @@ fn app_add_transaction(data: &mut AppData, ui: &AppUI, tx: Transaction) {
     let idx = data.id;
     data.id += 1;
     ui.list_model
-        .insert_with_values(None, &[0, 1], &[&idx, &tx.get_id()]);
+        .insert_with_values(None, &[0, 1, 2], &[&idx, &tx.get_id(), &label]);
     data.txs.insert(idx, tx);
 }
@@ -0,0 +1,367 @@
+use crate::entropy::Entropy;
+use crate::hash::{Hash, Hashable};
+use crate::keys::{PubKey, Signature};
+use crate::transaction::{self, SigningScheme, Timestamp, Transaction};
+use crate::workload::{Job, NextJob, Workload, LABEL_REGISTER, LABEL_TRANSFER};
+use base64::encode_config;
+use rust_sodium::crypto::sign::{ed25519::PublicKey, ed25519::SecretKey};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+// ========================================================================== //
+
+/// Number of seconds into the future of the timestamp of a future-timestamp
+/// attack. The timestamp is ahead of both the virtual clock of the run and the
+/// system clock, since the node judges it by its own clock.
+const FUTURE_SECS: u64 = 365 * 24 * 60 * 60;
+
+/// Number of bytes left of the output key of a truncated-key attack
+const TRUNCATED_KEY_LEN: usize = 16;
+
+// ========================================================================== //
+
+/// Kinds of transactions that the node must reject
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Attack {
+    /// Registration where the signature has been tampered with
+    BadSignature,
+    /// Registration that is signed by another key than the output key
+    WrongKey,
+    /// Transfer where the input key is not the output key of the previous
+    /// transaction of the bike
+    InputMismatch,
+    /// Second transfer of a bike from the same previous transaction
+    DoubleSpend,
+    /// Transaction that is sent again after the node accepted it
+    Replay,
+    /// Registration where the output key is too short
+    TruncatedKey,
+    /// Registration with a timestamp a year into the future
+    FutureTimestamp,
+    /// Registration with a zero timestamp
+    ZeroTimestamp,
+}
+
+/// All kinds of attacks
+pub const ATTACKS: [Attack; 8] = [
+    Attack::BadSignature,
+    Attack::WrongKey,
+    Attack::InputMismatch,
+    Attack::DoubleSpend,
+    Attack::Replay,
+    Attack::TruncatedKey,
+    Attack::FutureTimestamp,
+    Attack::ZeroTimestamp,
+];
+
+impl Attack {
+    /// Returns the name of the attack, which is also the label of the
+    /// transactions it sends
+    pub fn name(self) -> &'static str {
+        match self {
+            Attack::BadSignature => "bad-signature",
+            Attack::WrongKey => "wrong-key",
+            Attack::InputMismatch => "input-mismatch",
+            Attack::DoubleSpend => "double-spend",
+            Attack::Replay => "replay",
+            Attack::TruncatedKey => "truncated-key",
+            Attack::FutureTimestamp => "future-timestamp",
+            Attack::ZeroTimestamp => "zero-timestamp",
+        }
+    }
+
+    /// Returns a short description of the attack
+    pub fn description(self) -> &'static str {
+        match self {
+            Attack::BadSignature => "Bad signature",
+            Attack::WrongKey => "Signature by the wrong key",
+            Attack::InputMismatch => "Input does not match previous output",
+            Attack::DoubleSpend => "Double-spend",
+            Attack::Replay => "Replayed transaction",
+            Attack::TruncatedKey => "Truncated key",
+            Attack::FutureTimestamp => "Future timestamp",
+            Attack::ZeroTimestamp => "Zero timestamp",
+        }
+    }
+
+    /// Make the transactions of one attack on a new bike, signed with a
+    /// scheme. Keys and timestamps are taken from 'entropy'. The first job is
+    /// sent directly. Each of the following jobs is sent once the node has
+    /// accepted the job before it.
+    pub fn make_jobs(self, id: String, scheme: SigningScheme, entropy: &mut Entropy) -> Vec<Job> {
+        match self {
+            Attack::BadSignature => vec![make_bad_signature(id, scheme, entropy)],
+            Attack::WrongKey => {
+                let (pk, _) = entropy.gen_keypair();
+                let (_, sk_other) = entropy.gen_keypair();
+                let tx = make_signed(id, entropy.timestamp(), None, &pk, &sk_other, scheme);
+                vec![Job::new(self.name(), tx, None)]
+            }
+            Attack::InputMismatch => {
+                let (t0, sk0) = Transaction::debug_make_register_with(id.clone(), scheme, entropy);
+                let (pk_other, sk_other) = entropy.gen_keypair();
+                let (pk_out, _) = entropy.gen_keypair();
+                let tx = make_signed(
+                    id,
+                    entropy.timestamp(),
+                    Some(&pk_other),
+                    &pk_out,
+                    &sk_other,
+                    scheme,
+                );
+                vec![
+                    Job::new(LABEL_REGISTER, t0, Some(sk0)),
+                    Job::new(self.name(), tx, None),
+                ]
+            }
+            Attack::DoubleSpend => {
+                let (t0, sk0) = Transaction::debug_make_register_with(id, scheme, entropy);
+                let (t1, sk1) = Transaction::debug_make_transfer_with(&t0, &sk0, entropy);
+                let (t2, _) = Transaction::debug_make_transfer_with(&t0, &sk0, entropy);
+                vec![
+                    Job::new(LABEL_REGISTER, t0, Some(sk0)),
+                    Job::new(LABEL_TRANSFER, t1, Some(sk1)),
+                    Job::new(self.name(), t2, None),
+                ]
+            }
+            Attack::Replay => {
+                let (t0, sk0) = Transaction::debug_make_register_with(id, scheme, entropy);
+                vec![
+                    Job::new(LABEL_REGISTER, t0.clone(), Some(sk0)),
+                    Job::new(self.name(), t0, None),
+                ]
+            }
+            Attack::TruncatedKey => {
+                let (tx, _) = Transaction::debug_make_register_with(id, scheme, entropy);
+                let mut v: Value = serde_json::from_str(&tx.to_json()).unwrap();
+                let key = &tx.get_public_key_output().as_bytes()[..TRUNCATED_KEY_LEN];
+                v["publicKeyOutput"] = json!(encode_config(key, base64::URL_SAFE));
+                let mut job = Job::new(self.name(), tx, None);
+                job.body = Some(serde_json::to_string_pretty(&v).unwrap());
+                vec![job]
+            }
+            Attack::FutureTimestamp => {
+                let (pk, sk) = entropy.gen_keypair();
+                let now = entropy.timestamp().max(transaction::make_timestamp());
+                let ts = now + FUTURE_SECS;
+                vec![Job::new(
+                    self.name(),
+                    make_signed(id, ts, None, &pk, &sk, scheme),
+                    None,
+                )]
+            }
+            Attack::ZeroTimestamp => {
+                let (pk, sk) = entropy.gen_keypair();
+                vec![Job::new(
+                    self.name(),
+                    make_signed(id, 0, None, &pk, &sk, scheme),
+                    None,
+                )]
+            }
+        }
+    }
+}
+
+impl FromStr for Attack {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Attack, String> {
+        match ATTACKS.iter().find(|a| a.name() == s) {
+            Some(attack) => Ok(*attack),
+            None => Err(format!("Unknown attack '{}'", s)),
+        }
+    }
+}
+
+impl Display for Attack {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// ========================================================================== //
+
+/// Make a registration where the signature does not match the content
+pub fn make_bad_signature(id: String, scheme: SigningScheme, entropy: &mut Entropy) -> Job {
+    let (tx, _) = Transaction::debug_make_register_with(id, scheme, entropy);
+    let mut signature = *tx.get_signature();
+    signature.as_mut()[0] ^= 0xff;
+    let tx = Transaction::from_details(
+        tx.get_id().clone(),
+        tx.get_timestamp(),
+        *tx.get_public_key_input(),
+        *tx.get_public_key_output(),
+        signature,
+        scheme,
+    );
+    Job::new(Attack::BadSignature.name(), tx, None)
+}
+
+/// Make a transaction with the specified content, signed by 'sk'
+fn make_signed(
+    id: String,
+    timestamp: Timestamp,
+    input: Option<&PublicKey>,
+    output: &PublicKey,
+    sk: &SecretKey,
+    scheme: SigningScheme,
+) -> Transaction {
+    let input = input.map(PubKey::from);
+    let output = PubKey::from(output);
+    let signature = Signature::default();
+    let mut tx = Transaction::from_details(id, timestamp, input, output, signature, scheme);
+    tx.sign(sk);
+    tx
+}
+
+// ========================================================================== //
+
+/// Workload that performs a number of attacks of one kind, each on a new bike
+pub struct AttackWorkload {
+    /// List of names
+    names: Vec<String>,
+    /// Kind of attack
+    attack: Attack,
+    /// Signing scheme of the transactions
+    scheme: SigningScheme,
+    /// Source of the names, keys and timestamps
+    entropy: Entropy,
+    /// Number of attacks left to start
+    remaining: u32,
+    /// Jobs that are ready to be sent
+    queue: VecDeque<Job>,
+    /// Jobs that wait for the job with the hash they are indexed by
+    waiting: HashMap<Hash, Vec<Job>>,
+}
+
+impl AttackWorkload {
+    pub fn new(
+        names: Vec<String>,
+        attack: Attack,
+        count: u32,
+        scheme: SigningScheme,
+        entropy: Entropy,
+    ) -> AttackWorkload {
+        AttackWorkload {
+            names,
+            attack,
+            scheme,
+            entropy,
+            remaining: count,
+            queue: VecDeque::new(),
+            waiting: HashMap::new(),
+        }
+    }
+}
+
+impl Workload for AttackWorkload {
+    fn next_job(&mut self) -> NextJob {
+        if let Some(job) = self.queue.pop_front() {
+            return NextJob::Ready(job);
+        }
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            let id = self.entropy.gen_name(&self.names);
+            let mut jobs = self.attack.make_jobs(id, self.scheme, &mut self.entropy);
+            let first = jobs.remove(0);
+            if !jobs.is_empty() {
+                self.waiting.insert(first.tx.calc_hash(), jobs);
+            }
+            return NextJob::Ready(first);
+        }
+        match self.waiting.is_empty() {
+            true => NextJob::Done,
+            false => NextJob::Pending,
+        }
+    }
+
+    fn on_result(&mut self, job: &Job, accepted: bool) {
+        let mut rest = match self.waiting.remove(&job.tx.calc_hash()) {
+            Some(rest) => rest,
+            None => return,
+        };
+        // The attack cannot continue without the transaction before it
+        if !accepted {
+            return;
+        }
+        let next = rest.remove(0);
+        if !rest.is_empty() {
+            self.waiting.insert(next.tx.calc_hash(), rest);
+        }
+        self.queue.push_back(next);
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expect::{Ledger, Verdict};
+    use crate::transaction::SIGNING_SCHEMES;
+
+    #[test]
+    fn test_attack_transactions() {
+        let mut entropy = Entropy::default();
+        for scheme in SIGNING_SCHEMES.iter() {
+            let mut verify = |attack: Attack| {
+                let jobs = attack.make_jobs(format!("SN1337BIKE"), *scheme, &mut entropy);
+                let job = jobs.last().unwrap();
+                assert_eq!(job.label, attack.name());
+                Transaction::from_json(&job.body()).and_then(|tx| tx.verify())
+            };
+            assert!(verify(Attack::BadSignature).is_err());
+            assert!(verify(Attack::WrongKey).is_err());
+            assert_eq!(verify(Attack::TruncatedKey).unwrap_err().kind(), "length");
+
+            // These are only invalid in the context of the chain or the clock
+            assert_eq!(verify(Attack::InputMismatch), Ok(()));
+            assert_eq!(verify(Attack::DoubleSpend), Ok(()));
+            assert_eq!(verify(Attack::Replay), Ok(()));
+            assert_eq!(verify(Attack::FutureTimestamp), Ok(()));
+            assert_eq!(verify(Attack::ZeroTimestamp), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_future_timestamp_with_clock() {
+        // A virtual clock in the past does not make the attack a registration
+        let mut entropy = Entropy::new(Some(1), Some(1_600_000_000));
+        let jobs = Attack::FutureTimestamp.make_jobs(
+            format!("SN1337BIKE"),
+            SigningScheme::V1,
+            &mut entropy,
+        );
+        let verdict = Ledger::new().judge(&jobs[0]);
+        assert_eq!(
+            verdict,
+            Verdict::Reject(format!("timestamp is in the future"))
+        );
+    }
+
+    #[test]
+    fn test_double_spend_order() {
+        let names = vec![String::from("Bike")];
+        let entropy = Entropy::default();
+        let mut w = AttackWorkload::new(names, Attack::DoubleSpend, 1, SigningScheme::V1, entropy);
+        let take = |w: &mut AttackWorkload| match w.next_job() {
+            NextJob::Ready(job) => job,
+            _ => panic!("Expected a job"),
+        };
+
+        // Each transaction waits for the one before it to be accepted
+        let t0 = take(&mut w);
+        w.on_result(&t0, true);
+        let t1 = take(&mut w);
+        w.on_result(&t1, true);
+        let t2 = take(&mut w);
+        assert_eq!(t2.label, "double-spend");
+        assert_eq!(t1.tx.verify_is_next(&t0.tx), true);
+        assert_eq!(t2.tx.verify_is_next(&t0.tx), true);
+        assert!(match w.next_job() {
+            NextJob::Done => true,
+            _ => false,
+        });
+    }
+}
@@ -0,0 +1,299 @@
+use crate::hash::{self, Hash, Hashable};
+use crate::merkle::{MerkleProof, MerkleTree};
+use crate::transaction::{self, Timestamp, Transaction, TxError};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fmt::{self, Display, Formatter};
+
+// ========================================================================== //
+
+/// Reasons for a block to be invalid
+#[derive(Clone, Debug, PartialEq)]
+pub enum BlockError {
+    /// The Merkle root is not the root of the transactions of the block
+    BadMerkleRoot,
+    /// The transaction at the index is not valid
+    Transaction(usize, TxError),
+    /// The transaction at the index is the same as an earlier transaction
+    Duplicate(usize),
+}
+
+impl Display for BlockError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockError::BadMerkleRoot => write!(f, "Merkle root does not match the transactions"),
+            BlockError::Transaction(i, e) => write!(f, "transaction {} is not valid ({})", i, e),
+            BlockError::Duplicate(i) => write!(f, "transaction {} is a duplicate", i),
+        }
+    }
+}
+
+// ========================================================================== //
+
+/// Block of transactions. The header of the block is the hash of the previous
+/// block, the timestamp, the Merkle root of the transactions and the nonce,
+/// and the hash of the block is the hash of its header.
+#[derive(Clone, Debug)]
+pub struct Block {
+    /// Hash of the previous block, EMPTY_HASH for the first block
+    prev_hash: Hash,
+    /// seconds since unix epoch (1970)
+    timestamp: Timestamp,
+    /// Root of the Merkle tree over the hashes of the transactions
+    merkle_root: Hash,
+    /// Number that is varied to mine the block
+    nonce: u64,
+    /// Transactions of the block, in the order of the leaves of the tree
+    transactions: Vec<Transaction>,
+}
+
+impl Block {
+    /// Create a block of transactions after the block with a hash
+    pub fn new(prev_hash: Hash, transactions: Vec<Transaction>) -> Block {
+        Block {
+            prev_hash,
+            timestamp: transaction::make_timestamp(),
+            merkle_root: Block::tree_of(&transactions).root(),
+            nonce: 0,
+            transactions,
+        }
+    }
+
+    /// Create a block from its parts as they are, for example from a block of
+    /// a node. The Merkle root is not checked.
+    pub fn from_details(
+        prev_hash: Hash,
+        timestamp: Timestamp,
+        merkle_root: Hash,
+        nonce: u64,
+        transactions: Vec<Transaction>,
+    ) -> Block {
+        Block {
+            prev_hash,
+            timestamp,
+            merkle_root,
+            nonce,
+            transactions,
+        }
+    }
+
+    /// Returns the Merkle tree over the hashes of transactions
+    fn tree_of(transactions: &[Transaction]) -> MerkleTree {
+        MerkleTree::new(transactions.iter().map(|tx| tx.calc_hash()).collect())
+    }
+
+    /// Returns the Merkle tree of the transactions of the block
+    pub fn tree(&self) -> MerkleTree {
+        Block::tree_of(&self.transactions)
+    }
+
+    /// Returns the proof that the transaction with a hash is included in the
+    /// block, or None if the block does not have the transaction
+    pub fn proof(&self, tx_hash: &Hash) -> Option<MerkleProof> {
+        let tree = self.tree();
+        let index = tree.leaves().iter().position(|h| h == tx_hash)?;
+        tree.proof(index)
+    }
+
+    /// Returns whether a proof shows that the transaction with a hash is
+    /// included in the block, by the Merkle root in its header and the number
+    /// of its transactions
+    pub fn verify_proof(&self, tx_hash: &Hash, proof: &MerkleProof) -> bool {
+        proof.count == self.transactions.len() && proof.verify(tx_hash, &self.merkle_root)
+    }
+
+    /// Verify that the Merkle root matches the transactions, and that each
+    /// transaction is valid and included only once
+    pub fn verify(&self) -> Result<(), BlockError> {
+        let tree = self.tree();
+        if tree.root() != self.merkle_root {
+            return Err(BlockError::BadMerkleRoot);
+        }
+        let mut seen: HashSet<Hash> = HashSet::new();
+        for (i, (tx, hash)) in self.transactions.iter().zip(tree.leaves()).enumerate() {
+            if let Err(e) = tx.verify() {
+                return Err(BlockError::Transaction(i, e));
+            }
+            if !seen.insert(*hash) {
+                return Err(BlockError::Duplicate(i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Verify that this block is a valid next block, given that the previous
+    /// block was "prev"
+    pub fn verify_is_next(&self, prev: &Block) -> bool {
+        self.prev_hash == prev.calc_hash() && self.timestamp >= prev.timestamp
+    }
+
+    /// Returns the bytes of the header, which are hashed for the hash of the
+    /// block
+    pub fn header_to_u8(&self) -> Vec<u8> {
+        let mut buf = Vec::from(&self.prev_hash[..]);
+        buf.extend_from_slice(&self.timestamp.to_le_bytes());
+        buf.extend_from_slice(&self.merkle_root);
+        buf.extend_from_slice(&self.nonce.to_le_bytes());
+        buf
+    }
+
+    /// Returns the block as a JSON object with hex hashes, where the
+    /// transactions are the same objects that are sent to the node
+    pub fn to_json(&self) -> String {
+        let transactions: Vec<Value> = self
+            .transactions
+            .iter()
+            .map(|tx| serde_json::from_str(&tx.to_json()).unwrap())
+            .collect();
+        json!({
+            "prevHash": hash::hash_to_str(&self.prev_hash),
+            "timestamp": self.timestamp,
+            "merkleRoot": hash::hash_to_str(&self.merkle_root),
+            "nonce": self.nonce,
+            "transactions": transactions,
+        })
+        .to_string()
+    }
+
+    /// Parse a block from JSON. The block is not verified.
+    pub fn from_json(json: &str) -> Result<Block, String> {
+        let b: BlockJson = match serde_json::from_str(json) {
+            Ok(b) => b,
+            Err(e) => return Err(format!("Invalid block ({})", e)),
+        };
+        let parse_hash = |field: &str, s: &str| match hash::str_to_hash(s) {
+            Some(hash) => Ok(hash),
+            None => Err(format!("Invalid block (field '{}' is not a hash)", field)),
+        };
+        let prev_hash = parse_hash("prevHash", &b.prev_hash)?;
+        let merkle_root = parse_hash("merkleRoot", &b.merkle_root)?;
+        let mut transactions = Vec::with_capacity(b.transactions.len());
+        for (i, v) in b.transactions.iter().enumerate() {
+            match Transaction::from_json(&v.to_string()) {
+                Ok(tx) => transactions.push(tx),
+                Err(e) => return Err(format!("Invalid transaction {} of block ({})", i, e)),
+            }
+        }
+        Ok(Block::from_details(
+            prev_hash,
+            b.timestamp,
+            merkle_root,
+            b.nonce,
+            transactions,
+        ))
+    }
+
+    /// Set the nonce
+    pub fn set_nonce(&mut self, nonce: u64) {
+        self.nonce = nonce
+    }
+
+    pub fn get_nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    /// Returns the hash of the previous block
+    pub fn get_prev_hash(&self) -> &Hash {
+        &self.prev_hash
+    }
+
+    pub fn get_timestamp(&self) -> Timestamp {
+        self.timestamp
+    }
+
+    /// Returns the Merkle root of the transactions
+    pub fn get_merkle_root(&self) -> &Hash {
+        &self.merkle_root
+    }
+
+    /// Returns the transactions of the block
+    pub fn get_transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+}
+
+/// Block in JSON
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BlockJson {
+    prev_hash: String,
+    timestamp: Timestamp,
+    merkle_root: String,
+    nonce: u64,
+    transactions: Vec<Value>,
+}
+
+impl Hashable for Block {
+    fn calc_hash(&self) -> Hash {
+        hash::obj_hash(&self.header_to_u8())
+    }
+}
+
+/// Allow blocks to be printed.
+impl Display for Block {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Block:{{ hash: {}, prev_hash: {}, timestamp: {}, merkle_root: {}, nonce: {} }}",
+            hash::hash_to_str(&self.calc_hash()),
+            hash::hash_to_str(&self.prev_hash),
+            self.timestamp,
+            hash::hash_to_str(&self.merkle_root),
+            self.nonce
+        )?;
+        for tx in &self.transactions {
+            writeln!(f, "  {}", tx)?;
+        }
+        Ok(())
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::EMPTY_HASH;
+
+    #[test]
+    fn test_block() {
+        let (t0, sk0) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let (t1, _) = Transaction::debug_make_transfer(&t0, &sk0);
+        let (t2, _) = Transaction::debug_make_register(format!("SN4242BIKE"));
+        let first = Block::new(EMPTY_HASH, vec![t0.clone()]);
+        let block = Block::new(first.calc_hash(), vec![t1.clone(), t2.clone()]);
+        assert_eq!(first.verify(), Ok(()));
+        assert_eq!(block.verify(), Ok(()));
+        assert!(block.verify_is_next(&first));
+        assert!(!first.verify_is_next(&block));
+
+        // Every transaction has a proof against the root in the header
+        for tx in &[t1.clone(), t2.clone()] {
+            let proof = block.proof(&tx.calc_hash()).unwrap();
+            assert!(block.verify_proof(&tx.calc_hash(), &proof));
+            assert!(!first.verify_proof(&tx.calc_hash(), &proof));
+        }
+        assert!(block.proof(&t0.calc_hash()).is_none());
+
+        let forged = Block::from_details(
+            first.calc_hash(),
+            block.get_timestamp(),
+            *first.get_merkle_root(),
+            block.get_nonce(),
+            vec![t1.clone(), t2.clone()],
+        );
+        assert_eq!(forged.verify(), Err(BlockError::BadMerkleRoot));
+        assert!(forged.calc_hash() != block.calc_hash());
+
+        // The nonce is part of the hash, and JSON keeps the hash
+        let mut mined = block.clone();
+        mined.set_nonce(42);
+        assert!(mined.calc_hash() != block.calc_hash());
+        let parsed = Block::from_json(&mined.to_json()).unwrap();
+        assert_eq!(parsed.calc_hash(), mined.calc_hash());
+        assert_eq!(parsed.verify(), Ok(()));
+        assert!(Block::from_json("{}").is_err());
+        let twice = Block::new(EMPTY_HASH, vec![t1.clone(), t1]);
+        assert_eq!(twice.verify(), Err(BlockError::Duplicate(1)));
+    }
+}
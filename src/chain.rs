@@ -0,0 +1,267 @@
+use crate::hash::{Hash, Hashable};
+use crate::transaction::{Timestamp, Transaction, TxError};
+use std::fmt::{self, Display, Formatter};
+
+// ========================================================================== //
+
+/// Reasons for a transaction to break the chain of a bike
+#[derive(Clone, Debug, PartialEq)]
+pub enum LinkError {
+    /// The transaction is not valid on its own
+    Invalid(TxError),
+    /// The first transaction of the chain is a transfer
+    NotRegistered,
+    /// A transaction after the first one registers the bike again
+    Reregistered,
+    /// The id is not the id of the first transaction
+    IdMismatch {
+        /// Id of the first transaction
+        expected: String,
+        /// Id of the transaction
+        found: String,
+    },
+    /// The input key is not the output key of the previous transaction
+    InputMismatch,
+    /// The timestamp is before the timestamp of the previous transaction
+    TimestampDecreased {
+        /// Timestamp of the previous transaction
+        prev: Timestamp,
+        /// Timestamp of the transaction
+        found: Timestamp,
+    },
+    /// The transaction spends the same output as the earlier transaction at
+    /// the index
+    Fork(usize),
+    /// The transaction is the same as the earlier transaction at the index
+    Duplicate(usize),
+}
+
+impl Display for LinkError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            LinkError::Invalid(e) => write!(f, "transaction is not valid ({})", e),
+            LinkError::NotRegistered => write!(f, "chain does not start with a registration"),
+            LinkError::Reregistered => write!(f, "bike is registered again"),
+            LinkError::IdMismatch { expected, found } => {
+                write!(f, "id is '{}' instead of '{}'", found, expected)
+            }
+            LinkError::InputMismatch => write!(f, "input is not the output of the previous link"),
+            LinkError::TimestampDecreased { prev, found } => write!(
+                f,
+                "timestamp {} is before the previous timestamp {}",
+                found, prev
+            ),
+            LinkError::Fork(i) => write!(f, "forks from link {}, which spends the same output", i),
+            LinkError::Duplicate(i) => write!(f, "duplicate of link {}", i),
+        }
+    }
+}
+
+// ========================================================================== //
+
+/// Result of the verification of one transaction of a chain
+#[derive(Clone, Debug)]
+pub struct LinkReport {
+    /// Hash of the transaction
+    pub hash: Hash,
+    /// Every problem with the transaction, empty if the link is valid
+    pub errors: Vec<LinkError>,
+}
+
+impl LinkReport {
+    /// Returns whether the transaction is a valid link of the chain
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Returns the problems with the transaction on a single line, or 'ok' if
+    /// there are none
+    pub fn summary(&self) -> String {
+        match self.is_valid() {
+            true => String::from("ok"),
+            false => {
+                let errors: Vec<String> = self.errors.iter().map(|e| e.to_string()).collect();
+                errors.join("; ")
+            }
+        }
+    }
+}
+
+/// Result of the verification of a whole chain, with a report for each
+/// transaction in chain order
+#[derive(Clone, Debug, Default)]
+pub struct ChainReport {
+    pub links: Vec<LinkReport>,
+}
+
+impl ChainReport {
+    /// Returns whether every link of the chain is valid. An empty chain is
+    /// valid.
+    pub fn is_valid(&self) -> bool {
+        self.links.iter().all(|link| link.is_valid())
+    }
+
+    /// Returns the index of the first link that is not valid, if any
+    pub fn first_broken(&self) -> Option<usize> {
+        self.links.iter().position(|link| !link.is_valid())
+    }
+
+    /// Returns the number of links that are not valid
+    pub fn broken_count(&self) -> usize {
+        self.links.iter().filter(|link| !link.is_valid()).count()
+    }
+}
+
+// ========================================================================== //
+
+/// Verify the history of a bike, oldest transaction first. The chain must
+/// start with a registration, and each transaction after it must be a
+/// transfer of the same bike that is signed by the owner of the previous
+/// transaction, no earlier than it. No two transactions may spend the same
+/// output.
+pub fn verify_chain(chain: &[Transaction]) -> ChainReport {
+    let hashes: Vec<Hash> = chain.iter().map(|tx| tx.calc_hash()).collect();
+    let mut links = Vec::with_capacity(chain.len());
+    for (i, tx) in chain.iter().enumerate() {
+        let mut errors = Vec::new();
+        if let Err(e) = tx.verify() {
+            errors.push(LinkError::Invalid(e));
+        }
+        if let Some(j) = hashes[..i].iter().position(|h| h == &hashes[i]) {
+            errors.push(LinkError::Duplicate(j));
+        }
+
+        let prev = match i {
+            0 => {
+                if tx.has_input() {
+                    errors.push(LinkError::NotRegistered);
+                }
+                links.push(LinkReport {
+                    hash: hashes[i],
+                    errors,
+                });
+                continue;
+            }
+            _ => &chain[i - 1],
+        };
+        let root = &chain[0];
+        if tx.get_id() != root.get_id() {
+            errors.push(LinkError::IdMismatch {
+                expected: root.get_id().clone(),
+                found: tx.get_id().clone(),
+            });
+        }
+        match tx.get_public_key_input() {
+            None => errors.push(LinkError::Reregistered),
+            Some(input) => {
+                if input != prev.get_public_key_output() {
+                    errors.push(LinkError::InputMismatch);
+                }
+                let fork = chain[..i].iter().zip(&hashes[..i]).position(|(t, h)| {
+                    h != &hashes[i] && t.get_public_key_input() == &Some(*input)
+                });
+                if let Some(j) = fork {
+                    errors.push(LinkError::Fork(j));
+                }
+            }
+        }
+        if tx.get_timestamp() < prev.get_timestamp() {
+            errors.push(LinkError::TimestampDecreased {
+                prev: prev.get_timestamp(),
+                found: tx.get_timestamp(),
+            });
+        }
+        links.push(LinkReport {
+            hash: hashes[i],
+            errors,
+        });
+    }
+    ChainReport { links }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entropy::Entropy;
+    use crate::transaction::SigningScheme;
+    use rust_sodium::crypto::sign::ed25519::SecretKey;
+
+    /// Returns a copy of a transaction with another id and timestamp, signed
+    /// again with a secret key
+    fn resign(tx: &Transaction, id: &str, timestamp: Timestamp, sk: &SecretKey) -> Transaction {
+        let mut t = Transaction::from_details(
+            String::from(id),
+            timestamp,
+            *tx.get_public_key_input(),
+            *tx.get_public_key_output(),
+            *tx.get_signature(),
+            tx.get_scheme(),
+        );
+        t.sign(sk);
+        t
+    }
+
+    #[test]
+    fn test_verify_chain() {
+        let mut entropy = Entropy::new(Some(1), Some(1_600_000_000));
+        let id = format!("SN1337BIKE");
+        let (t0, sk0) = Transaction::debug_make_register_with(id, SigningScheme::V1, &mut entropy);
+        let (t1, sk1) = Transaction::debug_make_transfer_with(&t0, &sk0, &mut entropy);
+        let (t2, _) = Transaction::debug_make_transfer_with(&t1, &sk1, &mut entropy);
+        let report = verify_chain(&[t0.clone(), t1.clone(), t2.clone()]);
+        assert!(report.is_valid());
+        assert_eq!(report.links[2].hash, t2.calc_hash());
+        assert_eq!(report.links[2].summary(), "ok");
+        assert!(verify_chain(&[]).is_valid());
+
+        let errors = |chain: &[Transaction]| -> Vec<Vec<LinkError>> {
+            let report = verify_chain(chain);
+            report.links.into_iter().map(|link| link.errors).collect()
+        };
+
+        // Transfers without the registration
+        assert_eq!(
+            errors(&[t1.clone(), t2.clone()])[0],
+            vec![LinkError::NotRegistered]
+        );
+
+        // A second transfer of the registration forks the chain
+        let (other, _) = Transaction::debug_make_transfer_with(&t0, &sk0, &mut entropy);
+        assert_eq!(
+            errors(&[t0.clone(), t1.clone(), other])[2],
+            vec![LinkError::InputMismatch, LinkError::Fork(1)]
+        );
+
+        // Another bike, and a timestamp from before the registration
+        let moved = resign(&t1, "SN0000BIKE", t0.get_timestamp() - 1, &sk0);
+        assert_eq!(
+            errors(&[t0.clone(), moved])[1],
+            vec![
+                LinkError::IdMismatch {
+                    expected: t0.get_id().clone(),
+                    found: String::from("SN0000BIKE"),
+                },
+                LinkError::TimestampDecreased {
+                    prev: t0.get_timestamp(),
+                    found: t0.get_timestamp() - 1,
+                },
+            ]
+        );
+
+        // Replays and registrations after the root
+        let report = verify_chain(&[t0.clone(), t1.clone(), t1.clone(), t0.clone()]);
+        assert!(report.links[2].errors.contains(&LinkError::Duplicate(1)));
+        assert!(report.links[3].errors.contains(&LinkError::Reregistered));
+        assert_eq!(report.first_broken(), Some(2));
+        assert_eq!(report.broken_count(), 2);
+
+        // Signed by the new owner instead of the previous one
+        let tampered = resign(&t1, "SN1337BIKE", t1.get_timestamp(), &sk1);
+        let report = verify_chain(&[t0, tampered]);
+        assert!(match report.links[1].errors[0] {
+            LinkError::Invalid(_) => true,
+            _ => false,
+        });
+    }
+}
@@ -0,0 +1,981 @@
+use crate::attack::ATTACKS;
+use crate::block::Block;
+use crate::chain;
+use crate::confirm::{self, ConfirmEvent, ConfirmStats};
+use crate::entropy::Entropy;
+use crate::expect::Verdict;
+use crate::hash::{self, Hashable, EMPTY_HASH};
+use crate::load::{self, LoadConfig, LoadEvent, SendResult};
+use crate::mine;
+use crate::mock;
+use crate::names;
+use crate::profile::RateProfile;
+use crate::query;
+use crate::rest::{self, RestClient, RestConfig};
+use crate::scenario::Scenario;
+use crate::stats::RunStats;
+use crate::targets::Target;
+use crate::targets::{Strategy, Targets, STRATEGIES};
+use crate::transaction::{
+    Decoding, SigningScheme, Timestamp, Transaction, DECODINGS, SIGNING_SCHEMES,
+};
+use crate::wire::{self, Format, FORMATS};
+use crate::workload::{self, Job, Mode, Workload, LABEL_MANUAL};
+use rand::Rng;
+use std::fs;
+use std::io::{self, Read};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// ========================================================================== //
+
+/// Default URL of the transaction endpoint of the node
+pub const DEFAULT_URL: &str = "http://localhost:8000/transaction";
+
+const USAGE: &str = "\
+Usage: sim_client [COMMAND] [OPTIONS]
+
+Starts the graphical client when no command is given.
+
+Commands:
+  send [FILE]      Send the JSON transaction in FILE (or stdin) to the node
+  generate         Print new register transactions as JSON
+  run              Generate transactions and send them to the node. Fails if a
+                   request fails or the node does not give the verdict that
+                   the client expects for a transaction.
+  scenario FILE    Run the JSON scenario in FILE and check its assertions
+  chain ID         Print the chain of bike ID on the nodes that its
+                   transactions are sent to, and verify every link
+  mine             Register new bikes on the first node, mine the accepted
+                   transactions into blocks on top of the tip of the node,
+                   and submit the blocks to the node
+  mock-node        Run a local stand-in for the node that validates
+                   transactions and keeps the chains of the bikes in memory
+  help             Print this message
+
+Options:
+  --url <URL>      Transaction endpoint of a node. Repeat the option to send
+                   to several nodes. (default: {url})
+  --strategy <S>   Strategy for several nodes (default: round-robin):
+                   {strategies}
+  --weights <W,..> Comma-separated weight of each node for 'weighted'
+  --format <F,..>  Comma-separated wire format of each node, or one format
+                   for all nodes: {formats} (default: json)
+  --count <N>      Number of transactions to generate (default: 1)
+  --workers <N>    Number of concurrent workers for 'run' (default: {workers})
+  --mode <MODE>    Workload for 'run': 'register' registers new bikes, 'chain'
+                   registers bikes and transfers them between owners, or the
+                   name of an attack (default: register)
+  --attack <NAME>  Run an attack, same as '--mode <NAME>'. The count is the
+                   number of attacks.
+  --bikes <N>      Number of bikes to register in 'chain' mode (default: {bikes})
+  --signing <S>    Scheme of the signed bytes of generated transactions:
+                   {schemes} (default: legacy)
+  --decoding <M>   How strictly JSON transactions are parsed, by 'send' and
+                   'mock-node' and for the expected verdicts of 'run':
+                   {decodings} (default: normal)
+  --seed <N>       Seed for the names, keys and random choices of 'generate' and
+                   'run', and for picking random nodes. With '--clock' as well,
+                   a run with the same seed generates the same transactions,
+                   byte for byte, as long as the nodes give the same verdicts.
+  --clock <TS>     Take timestamps from a virtual clock that starts at the Unix
+                   time TS and advances one second per transaction
+  --listen <ADDR>  Address for 'mock-node' to listen on (default: {listen})
+  --block-interval <S>  Seconds between the blocks that 'mock-node' produces
+                   of the transactions it accepted (default: no blocks)
+  --confirm-timeout <S>  Track each transaction that 'run' sends until the node
+                   includes it in a block, for at most S seconds. The run
+                   fails if a transaction is not confirmed in time.
+
+Mining options:
+  --difficulty <B> Number of leading zero bits of the hash of a block, for
+                   'mine' and 'mock-node', at most {max_difficulty}
+                   (default: {difficulty})
+  --block-size <N> Number of transactions in a block (default: {block_size})
+  --forks <N>      Number of competing blocks to mine on the same previous
+                   block, to exercise the fork choice of the node (default: 1)
+
+Connection options:
+  --connect-timeout <S>  Seconds to wait for a connection (default: {connect})
+  --timeout <S>    Seconds to wait for a response (default: {timeout})
+  --retries <N>    Number of retries when the host of a node cannot be
+                   resolved, refuses the connection or is unavailable
+                   (default: {retries})
+
+Rate options for 'run' (sends on a schedule instead of back-to-back):
+  --rate <TPS>     Target rate in transactions per second
+  --ramp-up <S>    Seconds to ramp up from zero to the target rate (default: 0)
+  --hold <S>       Seconds to hold the target rate (default: {hold})
+  --ramp-down <S>  Seconds to ramp down from the target rate to zero (default: 0)
+
+Attacks (transactions that the node must reject):
+{attacks}";
+
+// ========================================================================== //
+
+/// Default number of seconds to hold the target rate
+const DEFAULT_HOLD: f64 = 10.0;
+
+// ========================================================================== //
+
+/// Exit code for a command that completed successfully
+pub const EXIT_OK: i32 = 0;
+
+/// Exit code for a command where one or more transactions failed or got an
+/// unexpected verdict
+pub const EXIT_FAILED: i32 = 1;
+
+/// Exit code for invalid command-line usage
+pub const EXIT_USAGE: i32 = 2;
+
+// ========================================================================== //
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Send { file: Option<String> },
+    Generate,
+    Run,
+    Scenario { file: Option<String> },
+    Chain { id: Option<String> },
+    Mine,
+    MockNode,
+    Help,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    /// Command to execute
+    command: Command,
+    /// Transaction endpoints of the nodes
+    targets: Targets,
+    /// Number of transactions to generate
+    count: u32,
+    /// Number of concurrent workers
+    workers: usize,
+    /// Rate profile for an open-loop run
+    profile: Option<RateProfile>,
+    /// Workload of the run
+    mode: Mode,
+    /// Number of bikes to register in chain mode
+    bikes: u32,
+    /// Signing scheme of generated transactions
+    signing: SigningScheme,
+    /// Decoding mode of JSON transactions
+    decoding: Decoding,
+    /// Seed for generated transactions
+    seed: Option<u64>,
+    /// Start of the virtual clock for generated transactions
+    clock: Option<Timestamp>,
+    /// Address for the mock node to listen on
+    listen: String,
+    /// Number of leading zero bits of the hash of a block
+    difficulty: u32,
+    /// Number of transactions in a mined block
+    block_size: usize,
+    /// Number of competing blocks to mine on the same previous block
+    forks: u32,
+    /// Time between the blocks that the mock node produces
+    block_interval: Option<Duration>,
+    /// Longest time to wait for a transaction to be included in a block, if
+    /// transactions are tracked
+    confirm_timeout: Option<Duration>,
+    /// Configuration of the REST client
+    rest: RestConfig,
+}
+
+// ========================================================================== //
+
+/// Run the command-line client with the specified arguments (excluding the
+/// program name). Returns the exit code of the process.
+pub fn run(args: &[String]) -> i32 {
+    let opts = match parse_args(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("error: {}\n", e);
+            eprint!("{}", usage());
+            return EXIT_USAGE;
+        }
+    };
+
+    match opts.command {
+        Command::Send { ref file } => cmd_send(&opts, file),
+        Command::Generate => cmd_generate(&opts),
+        Command::Run => cmd_run(&opts),
+        Command::Scenario {
+            file: Some(ref file),
+        } => cmd_scenario(file),
+        Command::Scenario { file: None } => {
+            eprintln!("error: Missing scenario file\n");
+            eprint!("{}", usage());
+            EXIT_USAGE
+        }
+        Command::Chain { id: Some(ref id) } => cmd_chain(&opts, id),
+        Command::Chain { id: None } => {
+            eprintln!("error: Missing bike id\n");
+            eprint!("{}", usage());
+            EXIT_USAGE
+        }
+        Command::Mine => cmd_mine(&opts),
+        Command::MockNode => {
+            match mock::serve(
+                &opts.listen,
+                opts.decoding,
+                opts.difficulty,
+                opts.block_interval,
+            ) {
+                Ok(_) => EXIT_OK,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    EXIT_FAILED
+                }
+            }
+        }
+        Command::Help => {
+            print!("{}", usage());
+            EXIT_OK
+        }
+    }
+}
+
+// ========================================================================== //
+
+fn usage() -> String {
+    let attacks: String = ATTACKS
+        .iter()
+        .map(|a| format!("  {:<17}{}\n", a.name(), a.description()))
+        .collect();
+    let strategies: Vec<&str> = STRATEGIES.iter().map(|s| s.name()).collect();
+    let formats: Vec<&str> = FORMATS.iter().map(|f| f.name()).collect();
+    let schemes: Vec<&str> = SIGNING_SCHEMES.iter().map(|s| s.name()).collect();
+    let decodings: Vec<&str> = DECODINGS.iter().map(|d| d.name()).collect();
+    USAGE
+        .replace("{url}", DEFAULT_URL)
+        .replace("{strategies}", &strategies.join(", "))
+        .replace("{formats}", &formats.join(", "))
+        .replace("{schemes}", &schemes.join(", "))
+        .replace("{decodings}", &decodings.join(", "))
+        .replace("{workers}", &load::DEFAULT_WORKERS.to_string())
+        .replace("{hold}", &DEFAULT_HOLD.to_string())
+        .replace("{bikes}", &workload::DEFAULT_BIKES.to_string())
+        .replace("{listen}", mock::DEFAULT_LISTEN)
+        .replace("{difficulty}", &mine::DEFAULT_DIFFICULTY.to_string())
+        .replace("{max_difficulty}", &mine::MAX_DIFFICULTY.to_string())
+        .replace("{block_size}", &mine::DEFAULT_BLOCK_SIZE.to_string())
+        .replace(
+            "{connect}",
+            &rest::DEFAULT_CONNECT_TIMEOUT.as_secs().to_string(),
+        )
+        .replace("{timeout}", &rest::DEFAULT_TIMEOUT.as_secs().to_string())
+        .replace("{retries}", &rest::DEFAULT_RETRIES.to_string())
+        .replace("{attacks}", &attacks)
+}
+
+// ========================================================================== //
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut it = args.iter();
+    let mut command = match it.next().map(|s| s.as_str()) {
+        Some("send") => Command::Send { file: None },
+        Some("generate") => Command::Generate,
+        Some("run") => Command::Run,
+        Some("scenario") => Command::Scenario { file: None },
+        Some("chain") => Command::Chain { id: None },
+        Some("mine") => Command::Mine,
+        Some("mock-node") => Command::MockNode,
+        Some("help") | Some("--help") | Some("-h") => Command::Help,
+        Some(c) => return Err(format!("Unknown command '{}'", c)),
+        None => return Err(format!("Missing command")),
+    };
+    let mut urls: Vec<String> = Vec::new();
+    let mut strategy = Strategy::default();
+    let mut weights: Option<Vec<f64>> = None;
+    let mut formats: Option<Vec<Format>> = None;
+    let mut count = 1;
+    let mut workers = load::DEFAULT_WORKERS;
+    let mut rate: Option<f64> = None;
+    let (mut ramp_up, mut hold, mut ramp_down) = (0.0, DEFAULT_HOLD, 0.0);
+    let mut mode = Mode::Register;
+    let mut bikes = workload::DEFAULT_BIKES;
+    let mut signing = SigningScheme::default();
+    let mut decoding = Decoding::default();
+    let mut seed: Option<u64> = None;
+    let mut clock: Option<Timestamp> = None;
+    let mut listen = String::from(mock::DEFAULT_LISTEN);
+    let mut difficulty = mine::DEFAULT_DIFFICULTY;
+    let mut block_size = mine::DEFAULT_BLOCK_SIZE;
+    let mut forks = 1;
+    let mut block_interval: Option<Duration> = None;
+    let mut confirm_timeout: Option<Duration> = None;
+    let mut rest = RestConfig::default();
+
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "--url" => urls.push(option_value(&mut it, arg)?.clone()),
+            "--strategy" => strategy = parse_option(&mut it, arg)?,
+            "--weights" => weights = Some(parse_list(&mut it, arg)?),
+            "--format" => formats = Some(parse_list(&mut it, arg)?),
+            "--count" => count = parse_option(&mut it, arg)?,
+            "--workers" => workers = parse_option(&mut it, arg)?,
+            "--rate" => rate = Some(parse_option(&mut it, arg)?),
+            "--ramp-up" => ramp_up = parse_option(&mut it, arg)?,
+            "--hold" => hold = parse_option(&mut it, arg)?,
+            "--ramp-down" => ramp_down = parse_option(&mut it, arg)?,
+            "--mode" => mode = parse_option(&mut it, arg)?,
+            "--attack" => mode = Mode::Attack(parse_option(&mut it, arg)?),
+            "--bikes" => bikes = parse_option(&mut it, arg)?,
+            "--signing" => signing = parse_option(&mut it, arg)?,
+            "--decoding" => decoding = parse_option(&mut it, arg)?,
+            "--seed" => seed = Some(parse_option(&mut it, arg)?),
+            "--clock" => clock = Some(parse_option(&mut it, arg)?),
+            "--listen" => listen = option_value(&mut it, arg)?.clone(),
+            "--difficulty" => difficulty = parse_option(&mut it, arg)?,
+            "--block-size" => block_size = parse_option(&mut it, arg)?,
+            "--forks" => forks = parse_option(&mut it, arg)?,
+            "--block-interval" => block_interval = Some(to_duration(parse_option(&mut it, arg)?)?),
+            "--confirm-timeout" => {
+                confirm_timeout = Some(to_duration(parse_option(&mut it, arg)?)?)
+            }
+            "--connect-timeout" => rest.connect_timeout = to_duration(parse_option(&mut it, arg)?)?,
+            "--timeout" => rest.timeout = to_duration(parse_option(&mut it, arg)?)?,
+            "--retries" => rest.retries = parse_option(&mut it, arg)?,
+            _ if arg.starts_with("--") => return Err(format!("Unknown option '{}'", arg)),
+            _ => match command {
+                Command::Send { file: None } => {
+                    command = Command::Send {
+                        file: Some(arg.clone()),
+                    }
+                }
+                Command::Scenario { file: None } => {
+                    command = Command::Scenario {
+                        file: Some(arg.clone()),
+                    }
+                }
+                Command::Chain { id: None } => {
+                    command = Command::Chain {
+                        id: Some(arg.clone()),
+                    }
+                }
+                _ => return Err(format!("Unexpected argument '{}'", arg)),
+            },
+        }
+    }
+
+    if workers == 0 {
+        return Err(format!("Number of workers must be at least 1"));
+    }
+    let capacity = names::capacity(&names::load());
+    if bikes as usize > capacity {
+        return Err(format!("Number of bikes must be at most {}", capacity));
+    }
+    if difficulty > mine::MAX_DIFFICULTY {
+        return Err(format!(
+            "Difficulty must be at most {} bits",
+            mine::MAX_DIFFICULTY
+        ));
+    }
+    if block_size == 0 || forks == 0 {
+        return Err(format!("Block size and forks must be at least 1"));
+    }
+    if urls.is_empty() {
+        urls.push(String::from(DEFAULT_URL));
+    }
+    let targets = Targets::new(urls, weights, strategy)?
+        .with_formats(formats)?
+        .with_seed(seed);
+    let profile = match rate {
+        Some(rate) => Some(make_profile(rate, ramp_up, hold, ramp_down)?),
+        None => None,
+    };
+
+    Ok(Options {
+        command,
+        targets,
+        count,
+        workers,
+        profile,
+        mode,
+        bikes,
+        signing,
+        decoding,
+        seed,
+        clock,
+        listen,
+        difficulty,
+        block_size,
+        forks,
+        block_interval,
+        confirm_timeout,
+        rest,
+    })
+}
+
+/// Parse the comma-separated list that follows an option
+fn parse_list<'a, I, T>(it: &mut I, name: &str) -> Result<Vec<T>, String>
+where
+    I: Iterator<Item = &'a String>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = option_value(it, name)?;
+    let mut list = Vec::new();
+    for item in value.split(',') {
+        match item.trim().parse::<T>() {
+            Ok(v) => list.push(v),
+            Err(e) => {
+                return Err(format!(
+                    "Invalid value '{}' for option '{}' ({})",
+                    item, name, e
+                ))
+            }
+        }
+    }
+    Ok(list)
+}
+
+/// Create a rate profile from a rate and the durations (in seconds) of the
+/// phases
+fn make_profile(rate: f64, ramp_up: f64, hold: f64, ramp_down: f64) -> Result<RateProfile, String> {
+    if !(rate > 0.0) {
+        return Err(format!("Rate must be greater than 0"));
+    }
+    Ok(RateProfile {
+        rate,
+        ramp_up: to_duration(ramp_up)?,
+        hold: to_duration(hold)?,
+        ramp_down: to_duration(ramp_down)?,
+    })
+}
+
+/// Convert a number of seconds to a duration
+fn to_duration(secs: f64) -> Result<Duration, String> {
+    match secs >= 0.0 && secs.is_finite() {
+        true => Ok(Duration::from_secs_f64(secs)),
+        false => Err(format!("Invalid duration '{}'", secs)),
+    }
+}
+
+/// Returns the value that follows an option
+fn option_value<'a, I>(it: &mut I, name: &str) -> Result<&'a String, String>
+where
+    I: Iterator<Item = &'a String>,
+{
+    match it.next() {
+        Some(v) => Ok(v),
+        None => Err(format!("Missing value for option '{}'", name)),
+    }
+}
+
+/// Parse the value that follows an option
+fn parse_option<'a, I, T>(it: &mut I, name: &str) -> Result<T, String>
+where
+    I: Iterator<Item = &'a String>,
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    let value = option_value(it, name)?;
+    match value.parse::<T>() {
+        Ok(v) => Ok(v),
+        Err(e) => Err(format!(
+            "Invalid value '{}' for option '{}' ({})",
+            value, name, e
+        )),
+    }
+}
+
+// ========================================================================== //
+
+/// Send a single transaction read from a file or stdin
+fn cmd_send(opts: &Options, file: &Option<String>) -> i32 {
+    let json = match file {
+        Some(path) => fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e)),
+        None => {
+            let mut s = String::new();
+            io::stdin()
+                .read_to_string(&mut s)
+                .map(|_| s)
+                .map_err(|e| format!("stdin: {}", e))
+        }
+    };
+    let json = match json {
+        Ok(json) => json,
+        Err(e) => {
+            eprintln!("error: Failed to read transaction ({})", e);
+            return EXIT_FAILED;
+        }
+    };
+
+    let client = match make_client(&opts.rest) {
+        Some(client) => client,
+        None => return EXIT_FAILED,
+    };
+    match Transaction::from_json_with(&json, opts.decoding) {
+        Ok(tx) => {
+            let mut accepted = true;
+            for target in opts.targets.pick(0, tx.get_id()) {
+                accepted = send_transaction(&client, target, &tx, Some(&json)) && accepted;
+            }
+            match accepted {
+                true => EXIT_OK,
+                false => EXIT_FAILED,
+            }
+        }
+        Err(e) => {
+            eprintln!("error: Invalid input ({})", e);
+            EXIT_FAILED
+        }
+    }
+}
+
+/// Print new register transactions
+fn cmd_generate(opts: &Options) -> i32 {
+    let names = names::load();
+    let mut entropy = Entropy::new(opts.seed, opts.clock);
+    for _ in 0..opts.count {
+        let id = entropy.gen_name(&names);
+        let (tx, _) = Transaction::debug_make_register_with(id, opts.signing, &mut entropy);
+        println!("{}", tx.to_json());
+    }
+    EXIT_OK
+}
+
+/// Print the chain of a bike on each node that its transactions are sent to,
+/// and verify each link of the chain
+fn cmd_chain(opts: &Options, id: &str) -> i32 {
+    let client = match make_client(&opts.rest) {
+        Some(client) => client,
+        None => return EXIT_FAILED,
+    };
+    let mut valid = true;
+    for target in opts.targets.pick(0, id) {
+        let chain = match query::fetch_chain(&client, target.url, id) {
+            Ok(chain) => chain,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                valid = false;
+                continue;
+            }
+        };
+        println!("{}: {} transactions", target.url, chain.len());
+        let report = chain::verify_chain(&chain);
+        for (i, (tx, link)) in chain.iter().zip(&report.links).enumerate() {
+            println!(
+                "  {:>3}  {}  {}  {}",
+                i,
+                hash::hash_to_str(&link.hash),
+                tx.get_timestamp(),
+                link.summary()
+            );
+        }
+        if let Some(first) = report.first_broken() {
+            println!(
+                "{} broken links, the first at {}",
+                report.broken_count(),
+                first
+            );
+            valid = false;
+        }
+    }
+    match valid {
+        true => EXIT_OK,
+        false => EXIT_FAILED,
+    }
+}
+
+/// Register new bikes on the first node, and mine the transactions that the
+/// node accepted into blocks. Each block is mined on top of the previous one,
+/// starting at the tip of the node, and submitted to the node. With forks,
+/// several competing blocks are mined on the same previous block, and the
+/// next block follows the first of them that the node accepted.
+fn cmd_mine(opts: &Options) -> i32 {
+    let client = match make_client(&opts.rest) {
+        Some(client) => client,
+        None => return EXIT_FAILED,
+    };
+    let target = opts.targets.target(0);
+    let names = names::load();
+    let mut entropy = Entropy::new(opts.seed, opts.clock);
+    let mut accepted = Vec::new();
+    for _ in 0..opts.count {
+        let id = entropy.gen_name(&names);
+        let (tx, _) = Transaction::debug_make_register_with(id, opts.signing, &mut entropy);
+        if send_transaction(&client, target, &tx, None) {
+            accepted.push(tx);
+        }
+    }
+
+    let mut prev_hash = match query::fetch_tip(&client, target.url) {
+        Ok(Some(tip)) => tip.calc_hash(),
+        Ok(None) => EMPTY_HASH,
+        Err(e) => {
+            eprintln!("error: Failed to fetch the tip ({})", e);
+            return EXIT_FAILED;
+        }
+    };
+    let mut valid = accepted.len() == opts.count as usize;
+    let (mut hashes, mut mining_time) = (0, Duration::from_secs(0));
+    for (i, txs) in accepted.chunks(opts.block_size).enumerate() {
+        let mut next = None;
+        for fork in 0..opts.forks {
+            let mut block = Block::new(prev_hash, txs.to_vec());
+            let started = Instant::now();
+            let limit = mine::attempt_limit(opts.difficulty);
+            let attempts = match mine::mine(&mut block, opts.difficulty, entropy.rng().gen(), limit)
+            {
+                Ok(attempts) => attempts,
+                Err(e) => {
+                    eprintln!("error: Failed to mine block {} ({})", i, e);
+                    return EXIT_FAILED;
+                }
+            };
+            let elapsed = started.elapsed();
+            hashes += attempts;
+            mining_time += elapsed;
+
+            let hash = block.calc_hash();
+            let name = match opts.forks {
+                1 => format!("block {}", i),
+                _ => format!("block {} fork {}", i, fork),
+            };
+            print!(
+                "{}: {} nonce {} ({} hashes, {:.1} ms): ",
+                name,
+                hash::hash_to_str(&hash),
+                block.get_nonce(),
+                attempts,
+                elapsed.as_secs_f64() * 1000.0
+            );
+            match mine::submit_block(&client, target.url, &block) {
+                Ok((r, s)) if (200..300).contains(&s) => {
+                    println!("accepted (code {}, {})", s, r);
+                    next = next.or(Some(hash));
+                }
+                Ok((r, s)) => {
+                    println!("rejected (code {}, {})", s, r);
+                    valid = false;
+                }
+                Err(e) => {
+                    println!("failed ({})", e);
+                    valid = false;
+                }
+            }
+        }
+        match next {
+            Some(hash) => prev_hash = hash,
+            None => break,
+        }
+    }
+
+    let secs = mining_time.as_secs_f64();
+    if secs > 0.0 {
+        println!(
+            "mined {} hashes in {:.2} s ({:.0} hashes/s)",
+            hashes,
+            secs,
+            hashes as f64 / secs
+        );
+    }
+    match query::fetch_tip(&client, target.url) {
+        Ok(Some(tip)) => println!("tip of node: {}", hash::hash_to_str(&tip.calc_hash())),
+        Ok(None) => println!("tip of node: none"),
+        Err(e) => {
+            eprintln!("error: Failed to fetch the tip ({})", e);
+            valid = false;
+        }
+    }
+    match valid {
+        true => EXIT_OK,
+        false => EXIT_FAILED,
+    }
+}
+
+/// Generate and send new transactions, and check the verdicts of the node
+fn cmd_run(opts: &Options) -> i32 {
+    let client = match make_client(&opts.rest) {
+        Some(client) => client,
+        None => return EXIT_FAILED,
+    };
+    let config = LoadConfig {
+        targets: opts.targets.clone(),
+        client,
+        workers: opts.workers,
+        profile: opts.profile.clone(),
+        duration: None,
+        decoding: opts.decoding,
+    };
+    let count = match opts.profile {
+        Some(ref profile) => profile.total_count().min(u32::max_value() as u64) as u32,
+        None => opts.count,
+    };
+    let entropy = Entropy::new(opts.seed, opts.clock);
+    let workload = opts
+        .mode
+        .make_workload(names::load(), count, opts.bikes, opts.signing, entropy);
+    let (stats, confirms) = run_load(config, workload, opts.confirm_timeout);
+    let confirmed = confirms.map(|c| c.expired == 0).unwrap_or(true);
+    match stats.errors == 0 && stats.mismatches == 0 && confirmed {
+        true => EXIT_OK,
+        false => EXIT_FAILED,
+    }
+}
+
+/// Run a scenario and check its assertions
+fn cmd_scenario(file: &str) -> i32 {
+    let scenario = match Scenario::load(file) {
+        Ok(scenario) => scenario,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return EXIT_FAILED;
+        }
+    };
+    let config = match scenario.load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return EXIT_FAILED;
+        }
+    };
+    let (stats, _) = run_load(config, scenario.workload(names::load()), None);
+    let failures = scenario.check(&stats);
+    for failure in &failures {
+        println!("assertion failed: {}", failure);
+    }
+    match failures.is_empty() {
+        true => EXIT_OK,
+        false => EXIT_FAILED,
+    }
+}
+
+/// Run a load, print the result of each transaction and the statistics of the
+/// run. With a confirmation timeout, each accepted transaction is tracked
+/// until the node includes it in a block, and the statistics of the
+/// confirmations are printed and returned as well.
+fn run_load(
+    config: LoadConfig,
+    workload: Box<dyn Workload>,
+    confirm_timeout: Option<Duration>,
+) -> (RunStats, Option<ConfirmStats>) {
+    let mut tracker = confirm_timeout.map(|t| confirm::start(config.client.clone(), t));
+    let handle = load::start(config, workload);
+    let mut stats = RunStats::new();
+    while let Some(event) = handle.recv() {
+        match event {
+            LoadEvent::Sent(result) => {
+                print_result(&result);
+                stats.record(&result);
+                if let Some(ref tracker) = tracker {
+                    tracker.track(&result);
+                }
+            }
+            LoadEvent::Done => break,
+        }
+    }
+    stats.finish();
+    println!("{}", stats);
+
+    let (mut tracker, timeout) = match (tracker.take(), confirm_timeout) {
+        (Some(tracker), Some(timeout)) => (tracker, timeout),
+        _ => return (stats, None),
+    };
+    tracker.close();
+    let mut confirms = ConfirmStats::new(timeout);
+    while let Some(event) = tracker.recv() {
+        match event {
+            ConfirmEvent::Confirmed(ref p, latency) => println!(
+                "{} {}: confirmed ({:.1} ms)",
+                p.label,
+                p.id,
+                latency.as_secs_f64() * 1000.0
+            ),
+            ConfirmEvent::Expired(ref p) => match p.error {
+                Some(ref e) => println!("{} {}: not confirmed ({})", p.label, p.id, e),
+                None => println!("{} {}: not confirmed", p.label, p.id),
+            },
+        }
+        confirms.record(&event);
+    }
+    println!("{}", confirms);
+    (stats, Some(confirms))
+}
+
+/// Create a REST client. Prints the error and returns None if the client
+/// could not be created.
+fn make_client(config: &RestConfig) -> Option<RestClient> {
+    match RestClient::new(config) {
+        Ok(client) => Some(client),
+        Err(e) => {
+            eprintln!("error: Failed to create HTTP client ({})", e);
+            None
+        }
+    }
+}
+
+/// Send a transaction and print the result. Returns whether the node accepted
+/// the transaction. JSON targets are sent the original JSON of the transaction
+/// unchanged, if there is one, so that the node decodes the same input as the
+/// client did.
+fn send_transaction(
+    client: &RestClient,
+    target: Target,
+    tx: &Transaction,
+    json: Option<&str>,
+) -> bool {
+    let body = match (target.format, json) {
+        (Format::Json, Some(json)) => json.as_bytes().to_vec(),
+        (format, _) => wire::encode(tx, format),
+    };
+    let sent_at = Instant::now();
+    let response = client.post(target.url, &body, target.format.content_type());
+    let mut job = Job::new(LABEL_MANUAL, tx.clone(), None);
+    job.expected = Verdict::of_content(tx);
+    let mut result = SendResult {
+        job,
+        url: String::from(target.url),
+        sent_at,
+        response,
+        latency: sent_at.elapsed(),
+        lag: Duration::from_secs(0),
+        mismatch: None,
+    };
+    result.check_expected();
+    print_result(&result)
+}
+
+/// Print the result of sending a transaction. Returns whether the node
+/// accepted the transaction.
+fn print_result(result: &SendResult) -> bool {
+    let id = format!("{} {}", result.job.label, result.job.tx.get_id());
+    let ms = result.latency.as_secs_f64() * 1000.0;
+    match &result.response {
+        Ok((r, s)) if result.is_accepted() => {
+            println!("{}: sent (code {}, {:.1} ms, {})", id, s, ms, r)
+        }
+        Ok((r, s)) => println!("{}: rejected (code {}, {:.1} ms, {})", id, s, ms, r),
+        Err(e) => println!("{}: failed ({:.1} ms, {})", id, ms, e),
+    }
+    if let Some(ref mismatch) = result.mismatch {
+        println!(
+            "{}: unexpected verdict from {}, {}",
+            id, result.url, mismatch
+        );
+    }
+    result.is_accepted()
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attack::Attack;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn parse(line: &str) -> Result<Options, String> {
+        parse_args(&args(line))
+    }
+
+    #[test]
+    fn test_parse_defaults() {
+        let opts = parse("run").unwrap();
+        let targets = Targets::new(vec![String::from(DEFAULT_URL)], None, Strategy::default())
+            .unwrap()
+            .with_formats(None)
+            .unwrap();
+        assert_eq!(opts.command, Command::Run);
+        assert_eq!(opts.targets, targets);
+        assert_eq!((opts.count, opts.workers), (1, load::DEFAULT_WORKERS));
+        assert_eq!(opts.profile, None);
+        assert_eq!(opts.mode, Mode::Register);
+        assert_eq!(opts.bikes, workload::DEFAULT_BIKES);
+        assert_eq!(opts.signing, SigningScheme::default());
+        assert_eq!(opts.decoding, Decoding::default());
+        assert_eq!((opts.seed, opts.clock), (None, None));
+        assert_eq!(opts.listen, mock::DEFAULT_LISTEN);
+        assert_eq!(opts.difficulty, mine::DEFAULT_DIFFICULTY);
+        assert_eq!(opts.block_size, mine::DEFAULT_BLOCK_SIZE);
+        assert_eq!(opts.forks, 1);
+        assert_eq!((opts.block_interval, opts.confirm_timeout), (None, None));
+        assert_eq!(opts.rest, RestConfig::default());
+    }
+
+    #[test]
+    fn test_parse_options() {
+        let opts = parse(
+            "run --url http://a/transaction --url http://b/transaction --strategy broadcast \
+             --count 5 --workers 2 --rate 50 --hold 2 --attack replay --seed 7 --timeout 1.5",
+        )
+        .unwrap();
+        assert_eq!(opts.targets.pick(0, "SN1337BIKE").len(), 2);
+        assert_eq!((opts.count, opts.workers), (5, 2));
+        assert_eq!(
+            opts.profile,
+            Some(RateProfile {
+                rate: 50.0,
+                ramp_up: Duration::from_secs(0),
+                hold: Duration::from_secs(2),
+                ramp_down: Duration::from_secs(0),
+            })
+        );
+        assert_eq!(opts.mode, Mode::Attack(Attack::Replay));
+        assert_eq!(opts.seed, Some(7));
+        assert_eq!(opts.rest.timeout, Duration::from_millis(1500));
+
+        // Each command takes at most its own positional argument
+        let file = Some(String::from("tx.json"));
+        assert_eq!(
+            parse("send tx.json").unwrap().command,
+            Command::Send { file }
+        );
+        let file = Some(String::from("s.json"));
+        assert_eq!(
+            parse("scenario s.json").unwrap().command,
+            Command::Scenario { file }
+        );
+        let id = Some(String::from("SN1337BIKE"));
+        assert_eq!(
+            parse("chain SN1337BIKE").unwrap().command,
+            Command::Chain { id }
+        );
+        assert_eq!(parse("chain").unwrap().command, Command::Chain { id: None });
+        assert!(parse("chain a b").is_err());
+        assert!(parse("run a").is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        for (line, msg) in &[
+            ("", "Missing command"),
+            ("frobnicate", "Unknown command 'frobnicate'"),
+            ("run --bogus", "Unknown option '--bogus'"),
+            ("run --count", "Missing value for option '--count'"),
+            ("run --count x", "Invalid value 'x' for option '--count'"),
+            ("run --mode x", "Invalid value 'x' for option '--mode'"),
+            ("run --workers 0", "Number of workers must be at least 1"),
+            ("run --rate 0", "Rate must be greater than 0"),
+            ("run --timeout -1", "Invalid duration '-1'"),
+            ("run --weights 1,2", "Number of weights (2)"),
+            ("run --bikes 1000001", "Number of bikes must be at most"),
+            ("mine --difficulty 33", "Difficulty must be at most 32 bits"),
+            ("mine --forks 0", "Block size and forks must be at least 1"),
+            ("send a b", "Unexpected argument 'b'"),
+        ] {
+            match parse(line) {
+                Ok(_) => panic!("'{}' should fail", line),
+                Err(e) => assert!(e.starts_with(msg), "'{}': {}", line, e),
+            }
+        }
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(run(&args("")), EXIT_USAGE);
+        assert_eq!(run(&args("run --workers 0")), EXIT_USAGE);
+        assert_eq!(run(&args("scenario")), EXIT_USAGE);
+        assert_eq!(run(&args("chain")), EXIT_USAGE);
+        assert_eq!(run(&args("help")), EXIT_OK);
+        assert_eq!(run(&args("send /nonexistent/tx.json")), EXIT_FAILED);
+        let scenario = run(&args("scenario /nonexistent/scenario.json"));
+        assert_eq!(scenario, EXIT_FAILED);
+    }
+}
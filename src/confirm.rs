@@ -0,0 +1,407 @@
+use crate::block::Block;
+use crate::hash::{self, Hash, Hashable, EMPTY_HASH};
+use crate::load::SendResult;
+use crate::query::{self, Inclusion};
+use crate::rest::RestClient;
+use crate::stats::Histogram;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ========================================================================== //
+
+/// Time between two polls of the inclusion status of the pending transactions
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// ========================================================================== //
+
+/// Blocks fetched from the nodes by hash, and the hashes of the blocks on the
+/// chain of each node. Blocks are kept across polls, while the chains are
+/// walked again from the tips on each poll.
+pub struct BlockCache {
+    blocks: HashMap<Hash, Block>,
+    chains: HashMap<String, HashSet<Hash>>,
+}
+
+impl BlockCache {
+    pub fn new() -> BlockCache {
+        BlockCache {
+            blocks: HashMap::new(),
+            chains: HashMap::new(),
+        }
+    }
+
+    /// Forget the chains of the nodes, so that the next check of a
+    /// transaction walks back from the current tip of its node
+    pub fn clear_chains(&mut self) {
+        self.chains.clear();
+    }
+
+    /// Returns the block with a hash, fetched from a node unless it is cached
+    fn block(
+        &mut self,
+        client: &RestClient,
+        endpoint: &str,
+        hash: &Hash,
+    ) -> Result<&Block, String> {
+        if !self.blocks.contains_key(hash) {
+            match query::fetch_block(client, endpoint, hash)? {
+                Some(block) if &block.calc_hash() == hash => {
+                    self.blocks.insert(*hash, block);
+                }
+                _ => {
+                    let hash = hash::hash_to_str(hash);
+                    return Err(format!("Node does not have the block {}", hash));
+                }
+            }
+        }
+        Ok(&self.blocks[hash])
+    }
+
+    /// Returns the hashes of the blocks on the chain of a node, from its tip
+    /// back to the first block
+    fn chain(&mut self, client: &RestClient, endpoint: &str) -> Result<&HashSet<Hash>, String> {
+        if !self.chains.contains_key(endpoint) {
+            let mut chain = HashSet::new();
+            if let Some(tip) = query::fetch_tip(client, endpoint)? {
+                let mut hash = tip.calc_hash();
+                self.blocks.insert(hash, tip);
+                while hash != EMPTY_HASH && chain.insert(hash) {
+                    hash = *self.block(client, endpoint, &hash)?.get_prev_hash();
+                }
+            }
+            self.chains.insert(String::from(endpoint), chain);
+        }
+        Ok(&self.chains[endpoint])
+    }
+}
+
+/// Returns whether a node has included a transaction in a block of its chain.
+/// The proof of inclusion from the node is checked against the Merkle root in
+/// the header of the block, and the block must be on the chain back from the
+/// tip of the node, so that a transaction in a block of another fork is not
+/// confirmed.
+pub fn is_confirmed(
+    client: &RestClient,
+    endpoint: &str,
+    tx_hash: &Hash,
+    cache: &mut BlockCache,
+) -> Result<bool, String> {
+    let (block_hash, proof) = match query::fetch_inclusion(client, endpoint, tx_hash)? {
+        Inclusion::Included { block, proof } => (block, proof),
+        Inclusion::Pending | Inclusion::Unknown => return Ok(false),
+    };
+    if !cache
+        .block(client, endpoint, &block_hash)?
+        .verify_proof(tx_hash, &proof)
+    {
+        return Err(format!("Proof of inclusion does not match the block"));
+    }
+    Ok(cache.chain(client, endpoint)?.contains(&block_hash))
+}
+
+// ========================================================================== //
+
+/// Transaction that was accepted by a node and waits to be included in a
+/// block
+#[derive(Clone, Debug)]
+pub struct Pending {
+    /// Kind of transaction
+    pub label: String,
+    /// Id of the bike
+    pub id: String,
+    /// Hash of the transaction
+    pub hash: Hash,
+    /// Transaction endpoint of the node that accepted the transaction
+    pub url: String,
+    /// Time when the transaction was sent
+    pub submitted: Instant,
+    /// Time when the inclusion of the transaction was last checked
+    pub checked: Instant,
+    /// Time between the last two checks, by which the confirm latency can be
+    /// late
+    pub granularity: Duration,
+    /// Last error from checking the inclusion of the transaction
+    pub error: Option<String>,
+}
+
+impl Pending {
+    /// Create the pending transaction of a result. Returns None if the node
+    /// did not accept the transaction.
+    pub fn from_result(result: &SendResult) -> Option<Pending> {
+        if !result.is_accepted() {
+            return None;
+        }
+        Some(Pending {
+            label: result.job.label.clone(),
+            id: result.job.tx.get_id().clone(),
+            hash: result.job.tx.calc_hash(),
+            url: result.url.clone(),
+            submitted: result.sent_at,
+            checked: result.sent_at,
+            granularity: Duration::from_secs(0),
+            error: None,
+        })
+    }
+}
+
+/// Outcome of tracking a transaction
+#[derive(Clone, Debug)]
+pub enum ConfirmEvent {
+    /// The transaction was included in a block, after the specified time
+    /// since it was sent
+    Confirmed(Pending, Duration),
+    /// The transaction was not included within the timeout
+    Expired(Pending),
+}
+
+/// Transactions that wait to be included in a block, in the order they were
+/// sent
+pub struct Tracker {
+    pending: Vec<Pending>,
+    /// Longest time to wait for a transaction
+    timeout: Duration,
+}
+
+impl Tracker {
+    pub fn new(timeout: Duration) -> Tracker {
+        Tracker {
+            pending: Vec::new(),
+            timeout,
+        }
+    }
+
+    /// Start tracking a transaction
+    pub fn track(&mut self, pending: Pending) {
+        self.pending.push(pending);
+    }
+
+    /// Returns whether no transactions are being tracked
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Check each tracked transaction once. Transactions that are confirmed,
+    /// and transactions that have waited longer than the timeout, are no
+    /// longer tracked. A transaction is confirmed as of the start of the poll,
+    /// so its latency is only as precise as the time between two polls.
+    pub fn poll<F>(&mut self, now: Instant, mut is_confirmed: F) -> Vec<ConfirmEvent>
+    where
+        F: FnMut(&Pending) -> Result<bool, String>,
+    {
+        let mut events = Vec::new();
+        let mut pending = Vec::with_capacity(self.pending.len());
+        for mut p in self.pending.drain(..) {
+            let waited = now.saturating_duration_since(p.submitted);
+            p.granularity = now.saturating_duration_since(p.checked);
+            p.checked = now;
+            match is_confirmed(&p) {
+                Ok(true) => {
+                    events.push(ConfirmEvent::Confirmed(p, waited));
+                    continue;
+                }
+                Ok(false) => {}
+                Err(e) => p.error = Some(e),
+            }
+            match waited > self.timeout {
+                true => events.push(ConfirmEvent::Expired(p)),
+                false => pending.push(p),
+            }
+        }
+        self.pending = pending;
+        events
+    }
+}
+
+// ========================================================================== //
+
+/// Handle to a tracker that runs on its own thread
+pub struct ConfirmHandle {
+    /// Transactions to track, None once no more will be tracked
+    tracked: Option<Sender<Pending>>,
+    /// Outcomes of the tracked transactions
+    events: Receiver<ConfirmEvent>,
+}
+
+impl ConfirmHandle {
+    /// Track the transaction of a result, if the node accepted it
+    pub fn track(&self, result: &SendResult) {
+        if let (Some(tracked), Some(pending)) = (&self.tracked, Pending::from_result(result)) {
+            let _ = tracked.send(pending);
+        }
+    }
+
+    /// Stop taking new transactions. The tracker shuts down once every
+    /// tracked transaction is confirmed or has expired.
+    pub fn close(&mut self) {
+        self.tracked = None;
+    }
+
+    /// Returns the next outcome, blocking until one is available. Returns
+    /// None once the tracker has shut down.
+    pub fn recv(&self) -> Option<ConfirmEvent> {
+        self.events.recv().ok()
+    }
+}
+
+/// Start a tracker that polls the nodes for the inclusion of transactions
+/// until they are confirmed or have waited longer than the timeout
+pub fn start(client: RestClient, timeout: Duration) -> ConfirmHandle {
+    let (tracked_tx, tracked_rx) = mpsc::channel::<Pending>();
+    let (events_tx, events_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut tracker = Tracker::new(timeout);
+        let mut cache = BlockCache::new();
+        let mut open = true;
+        while open || !tracker.is_empty() {
+            loop {
+                match tracked_rx.try_recv() {
+                    Ok(pending) => tracker.track(pending),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        open = false;
+                        break;
+                    }
+                }
+            }
+            cache.clear_chains();
+            let events = tracker.poll(Instant::now(), |p| {
+                is_confirmed(&client, &p.url, &p.hash, &mut cache)
+            });
+            for event in events {
+                if events_tx.send(event).is_err() {
+                    return;
+                }
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    });
+    ConfirmHandle {
+        tracked: Some(tracked_tx),
+        events: events_rx,
+    }
+}
+
+// ========================================================================== //
+
+/// Statistics of the confirmations of a run
+#[derive(Clone, Debug)]
+pub struct ConfirmStats {
+    /// Number of transactions that were included in a block
+    pub confirmed: u64,
+    /// Number of transactions that were not included within the timeout
+    pub expired: u64,
+    /// Time from sending each confirmed transaction until it was found in a
+    /// block
+    pub latency: Histogram,
+    /// Time between two checks of each confirmed transaction, which is the
+    /// precision of its confirm latency
+    pub granularity: Histogram,
+    /// Longest time that a transaction was waited for
+    pub timeout: Duration,
+}
+
+impl ConfirmStats {
+    pub fn new(timeout: Duration) -> ConfirmStats {
+        ConfirmStats {
+            confirmed: 0,
+            expired: 0,
+            latency: Histogram::new(),
+            granularity: Histogram::new(),
+            timeout,
+        }
+    }
+
+    /// Record the outcome of a tracked transaction
+    pub fn record(&mut self, event: &ConfirmEvent) {
+        match event {
+            ConfirmEvent::Confirmed(p, latency) => {
+                self.confirmed += 1;
+                self.latency.record(*latency);
+                self.granularity.record(p.granularity);
+            }
+            ConfirmEvent::Expired(_) => self.expired += 1,
+        }
+    }
+}
+
+/// Print the statistics as a short summary
+impl Display for ConfirmStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        writeln!(
+            f,
+            "confirmed {} of {} transactions, {} not confirmed within {:.0} s",
+            self.confirmed,
+            self.confirmed + self.expired,
+            self.expired,
+            self.timeout.as_secs_f64()
+        )?;
+        writeln!(
+            f,
+            "confirm latency p50 {:.1} ms, p90 {:.1} ms, p99 {:.1} ms, max {:.1} ms",
+            ms(self.latency.percentile(50.0)),
+            ms(self.latency.percentile(90.0)),
+            ms(self.latency.percentile(99.0)),
+            ms(self.latency.max())
+        )?;
+        write!(
+            f,
+            "confirm polls every {:.0} ms, time between checks p50 {:.1} ms, max {:.1} ms",
+            ms(POLL_INTERVAL),
+            ms(self.granularity.percentile(50.0)),
+            ms(self.granularity.max())
+        )
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::Transaction;
+    use crate::workload::Job;
+
+    #[test]
+    fn test_tracker() {
+        let start = Instant::now();
+        let mut tracker = Tracker::new(Duration::from_secs(10));
+        let mut stats = ConfirmStats::new(Duration::from_secs(10));
+        for name in &["SN1337BIKE", "SN4242BIKE"] {
+            let (tx, _) = Transaction::debug_make_register(String::from(*name));
+            let job = Job::new("register", tx, None);
+            let response = Ok((String::new(), 200));
+            let mut result = SendResult::debug_new(job, response, Duration::from_millis(5));
+            result.sent_at = start;
+            tracker.track(Pending::from_result(&result).unwrap());
+        }
+
+        // The first bike is included after 2 s, the second one never is
+        let is_confirmed = |p: &Pending| match p.id.as_str() {
+            "SN1337BIKE" => Ok(true),
+            _ => Err(String::from("not found")),
+        };
+        let events = tracker.poll(start + Duration::from_secs(2), is_confirmed);
+        assert_eq!(events.len(), 1);
+        events.iter().for_each(|e| stats.record(e));
+        assert!(tracker
+            .poll(start + Duration::from_secs(10), is_confirmed)
+            .is_empty());
+        let events = tracker.poll(start + Duration::from_secs(11), is_confirmed);
+        match events[0] {
+            ConfirmEvent::Expired(ref p) => {
+                assert_eq!(p.id, "SN4242BIKE");
+                assert_eq!(p.error.as_ref().unwrap(), "not found");
+            }
+            _ => panic!("expected the transaction to expire"),
+        }
+        events.iter().for_each(|e| stats.record(e));
+        assert!(tracker.is_empty());
+        assert_eq!((stats.confirmed, stats.expired), (1, 1));
+        assert_eq!(stats.latency.max(), Duration::from_secs(2));
+        assert_eq!(stats.granularity.max(), Duration::from_secs(2));
+        assert!(stats.to_string().contains("\nconfirm polls every 200 ms"));
+    }
+}
@@ -0,0 +1,275 @@
+use crate::expect::Verdict;
+use crate::transaction::{Decoding, Transaction, TxError};
+use serde_json::Value;
+
+// ========================================================================== //
+
+/// Problem in the JSON of a transaction, with the part of the text that it is
+/// about. Offsets count characters, like the offsets of a text buffer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Offset of the first character of the problem
+    pub start: usize,
+    /// Offset after the last character of the problem
+    pub end: usize,
+    /// Description of the problem, starting with the name of the field if the
+    /// problem is about one
+    pub message: String,
+}
+
+/// Check the JSON of a transaction, parsed with a decoding mode. Returns the
+/// first problem that the node would reject the transaction for, or None if
+/// the transaction is valid on its own.
+pub fn diagnose(json: &str, decoding: Decoding) -> Option<Diagnostic> {
+    if let Err(e) = serde_json::from_str::<Value>(json) {
+        let msg = e.to_string();
+        let msg = match msg.rfind(" at line ") {
+            Some(end) => String::from(&msg[..end]),
+            None => msg,
+        };
+        return Some(at_position(json, e.line(), e.column(), msg));
+    }
+    let tx = match Transaction::from_json_with(json, decoding) {
+        Ok(tx) => tx,
+        Err(e) => return Some(of_error(json, &e)),
+    };
+    if let Err(e) = tx.verify() {
+        return Some(of_error(json, &e));
+    }
+    // The signature is valid, so only the timestamp can be rejected
+    match Verdict::of_content(&tx) {
+        Some(Verdict::Reject(reason)) => Some(at_field(json, "timestamp", None, reason)),
+        _ => None,
+    }
+}
+
+/// Returns the diagnostic for an error in a transaction that is valid JSON
+fn of_error(json: &str, e: &TxError) -> Diagnostic {
+    match e {
+        TxError::UnknownField(field) => at_key(json, field, format!("unknown field")),
+        TxError::Field { field, expected } => {
+            let msg = format!("missing or not a {}", expected);
+            at_field(json, field, None, msg)
+        }
+        TxError::Decode {
+            field,
+            offset: Some(offset),
+            ..
+        } => {
+            let msg = format!("invalid base64 at offset {}", offset);
+            at_field(json, field, Some(*offset), msg)
+        }
+        TxError::Decode { field, error, .. } => {
+            at_field(json, field, None, format!("invalid base64 ({})", error))
+        }
+        TxError::Length {
+            field,
+            expected,
+            found,
+        } => {
+            let msg = format!("{} bytes instead of {}", found, expected);
+            at_field(json, field, None, msg)
+        }
+        TxError::Verify(e) => at_field(json, "signature", None, e.to_string()),
+        TxError::Json(_) | TxError::Binary(_) => at_position(json, 1, 1, e.to_string()),
+    }
+}
+
+// ========================================================================== //
+
+/// Returns a diagnostic for the character at a one-based line and column, as
+/// reported by the JSON parser
+fn at_position(json: &str, line: usize, column: usize, message: String) -> Diagnostic {
+    let line_start = json
+        .split('\n')
+        .take(line.saturating_sub(1))
+        .map(|l| l.len() + 1)
+        .sum::<usize>();
+    let mut idx = (line_start + column.saturating_sub(1)).min(json.len());
+    while !json.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    let total = json.chars().count();
+    let start = char_offset(json, idx).min(total.saturating_sub(1));
+    Diagnostic {
+        start,
+        end: (start + 1).min(total),
+        message,
+    }
+}
+
+/// Returns a diagnostic for the value of a field, or for the character at an
+/// offset in a string value. A field that is missing is reported at the start
+/// of the object.
+fn at_field(json: &str, field: &str, offset: Option<usize>, message: String) -> Diagnostic {
+    let message = format!("{}: {}", field, message);
+    let (start, end) = match find_value(json, field) {
+        Some((start, end)) => {
+            let (start, end) = (char_offset(json, start), char_offset(json, end));
+            match offset {
+                // Skip the opening quote
+                Some(offset) if start + 1 + offset < end => {
+                    (start + 1 + offset, start + 2 + offset)
+                }
+                _ => (start, end),
+            }
+        }
+        None => {
+            let start = char_offset(json, json.len() - json.trim_start().len());
+            (start, start + 1)
+        }
+    };
+    Diagnostic {
+        start,
+        end,
+        message,
+    }
+}
+
+/// Returns a diagnostic for the name of a field
+fn at_key(json: &str, field: &str, message: String) -> Diagnostic {
+    let message = format!("{}: {}", field, message);
+    let quoted = serde_json::to_string(field).unwrap();
+    let start = json.find(&quoted).unwrap_or(0);
+    let (start, end) = (start, (start + quoted.len()).min(json.len()));
+    Diagnostic {
+        start: char_offset(json, start),
+        end: char_offset(json, end),
+        message,
+    }
+}
+
+/// Returns the number of characters before a byte index
+fn char_offset(json: &str, idx: usize) -> usize {
+    json[..idx].chars().count()
+}
+
+/// Returns the byte range of the value of a key of the top-level object
+fn find_value(json: &str, key: &str) -> Option<(usize, usize)> {
+    let bytes = json.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => depth -= 1,
+            b'"' => {
+                let end = string_end(bytes, i);
+                let colon = skip_whitespace(bytes, end);
+                if depth == 1 && colon < bytes.len() && bytes[colon] == b':' {
+                    let name = serde_json::from_str::<String>(&json[i..end]);
+                    if name.map(|name| name == key).unwrap_or(false) {
+                        let start = skip_whitespace(bytes, colon + 1);
+                        return Some((start, value_end(bytes, start)));
+                    }
+                }
+                i = end;
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Returns the index after the closing quote of the string that starts at
+/// 'start'
+fn string_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Returns the index after the value that starts at 'start'
+fn value_end(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'"' => {
+                i = string_end(bytes, i);
+                if depth == 0 {
+                    return i;
+                }
+                continue;
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' if depth == 0 => break,
+            b'}' | b']' => depth -= 1,
+            b',' if depth == 0 => break,
+            _ => {}
+        }
+        i += 1;
+    }
+    while i > start && bytes[i - 1].is_ascii_whitespace() {
+        i -= 1;
+    }
+    i
+}
+
+/// Returns the index of the first character from 'i' that is not whitespace
+fn skip_whitespace(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diagnose() {
+        let (tx, _) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let json = tx.to_json();
+        let diagnose = |json: &str| super::diagnose(json, Decoding::Normal);
+        assert_eq!(diagnose(&json), None);
+
+        // Syntax errors are reported where the parser stopped
+        let d = diagnose("{\n  \"id\": ,\n}").unwrap();
+        assert_eq!(d.start, 10);
+        assert!(d.message.starts_with("expected value"));
+
+        // Field errors name the field and point into its value
+        let mut v: Value = serde_json::from_str(&json).unwrap();
+        v["publicKeyOutput"] = json!("abc!def");
+        let text = serde_json::to_string_pretty(&v).unwrap();
+        let d = diagnose(&text).unwrap();
+        assert_eq!(d.message, "publicKeyOutput: invalid base64 at offset 3");
+        assert_eq!(&text[d.start..d.end], "!");
+
+        v["publicKeyOutput"] = json!(tx.get_public_key_output().to_base64());
+        v["id"] = json!("SN1337BIKF");
+        let text = serde_json::to_string_pretty(&v).unwrap();
+        let d = diagnose(&text).unwrap();
+        assert_eq!(d.message, "signature: signature is not valid");
+        assert_eq!(&text[d.start..d.end], v["signature"].to_string());
+
+        let d = diagnose("{ \"timestamp\": 1 }").unwrap();
+        assert_eq!(d.message, "id: missing or not a string");
+        assert_eq!(d.start, 0);
+
+        // Hex keys and unknown fields depend on the decoding mode
+        let mut v: Value = serde_json::from_str(&json).unwrap();
+        v["publicKeyOutput"] = json!(tx.get_public_key_output().to_hex());
+        let text = v.to_string();
+        assert!(diagnose(&text).is_some());
+        assert_eq!(super::diagnose(&text, Decoding::Lenient), None);
+        let mut v: Value = serde_json::from_str(&json).unwrap();
+        v["extra"] = json!(1);
+        let text = v.to_string();
+        assert_eq!(diagnose(&text), None);
+        let d = super::diagnose(&text, Decoding::Strict).unwrap();
+        assert_eq!(d.message, "extra: unknown field");
+    }
+}
@@ -0,0 +1,98 @@
+use crate::names;
+use crate::transaction::{self, Timestamp};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use rust_sodium::crypto::sign::{self, ed25519::PublicKey, ed25519::SecretKey};
+
+// ========================================================================== //
+
+/// Number of seconds that the virtual clock advances for each timestamp
+pub const CLOCK_STEP: u64 = 1;
+
+// ========================================================================== //
+
+/// Source of the names, keys and timestamps of generated transactions, and of
+/// the random choices of workloads. Keys are derived from the random number
+/// generator, so a source with a seed and a virtual clock generates the same
+/// transactions, byte for byte, in every run.
+pub struct Entropy {
+    /// Random number generator
+    rng: StdRng,
+    /// Next timestamp of the virtual clock, or None to use the system clock
+    clock: Option<Timestamp>,
+}
+
+impl Entropy {
+    /// Create a source. Without a seed, the generator is seeded by the
+    /// operating system. With a start time, timestamps are taken from a
+    /// virtual clock that starts at that time and advances by CLOCK_STEP for
+    /// each timestamp.
+    pub fn new(seed: Option<u64>, clock: Option<Timestamp>) -> Entropy {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Entropy { rng, clock }
+    }
+
+    /// Returns the random number generator
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    /// Generate a random name from a list of names
+    pub fn gen_name(&mut self, names: &[String]) -> String {
+        names::gen_rand_name(names, &mut self.rng)
+    }
+
+    /// Generate a key pair from a seed that is drawn from the generator
+    pub fn gen_keypair(&mut self) -> (PublicKey, SecretKey) {
+        let mut bytes = [0; sign::SEEDBYTES];
+        self.rng.fill_bytes(&mut bytes);
+        let seed = sign::Seed::from_slice(&bytes).expect("Failed to generate seed");
+        sign::keypair_from_seed(&seed)
+    }
+
+    /// Returns the current time of the virtual clock and advances it, or the
+    /// time of the system clock if there is no virtual clock
+    pub fn timestamp(&mut self) -> Timestamp {
+        match self.clock {
+            Some(ref mut now) => {
+                let ts = *now;
+                *now += CLOCK_STEP;
+                ts
+            }
+            None => transaction::make_timestamp(),
+        }
+    }
+}
+
+/// Source without a seed that uses the system clock
+impl Default for Entropy {
+    fn default() -> Entropy {
+        Entropy::new(None, None)
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_entropy() {
+        let names = vec![String::from("Anna"), String::from("Bertil")];
+        let gen = |seed| {
+            let mut e = Entropy::new(Some(seed), Some(1_600_000_000));
+            let (pk, _) = e.gen_keypair();
+            (e.gen_name(&names), pk, e.timestamp(), e.timestamp())
+        };
+        let first = gen(42);
+        assert_eq!(gen(42), first);
+        assert!(gen(43).1 != first.1);
+        assert_eq!(
+            (first.2, first.3),
+            (1_600_000_000, 1_600_000_000 + CLOCK_STEP)
+        );
+    }
+}
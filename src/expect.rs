@@ -0,0 +1,280 @@
+use crate::hash::{Hash, Hashable};
+use crate::rest::RestError;
+use crate::transaction::{self, Decoding, Transaction};
+use crate::workload::Job;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+
+// ========================================================================== //
+
+/// Number of seconds a timestamp may be ahead of the clock of the client
+/// before the node is expected to reject the transaction
+const MAX_CLOCK_SKEW: u64 = 60;
+
+/// Longest part of a response body that is quoted in a mismatch
+const MAX_QUOTED_BODY: usize = 80;
+
+// ========================================================================== //
+
+/// Verdict that the node is expected to give on a transaction
+#[derive(Clone, Debug, PartialEq)]
+pub enum Verdict {
+    /// The node must accept the transaction
+    Accept,
+    /// The node must reject the transaction, for the specified reason
+    Reject(String),
+}
+
+impl Verdict {
+    /// Returns the verdict for a transaction that can be derived without any
+    /// knowledge of the chain, from its signature and timestamp. Returns None
+    /// if the transaction is valid on its own.
+    pub fn of_content(tx: &Transaction) -> Option<Verdict> {
+        if let Err(e) = tx.verify() {
+            return Some(Verdict::Reject(e.to_string()));
+        }
+        let timestamp = tx.get_timestamp();
+        if timestamp == 0 {
+            return Some(Verdict::Reject(format!("timestamp is zero")));
+        }
+        if timestamp > transaction::make_timestamp() + MAX_CLOCK_SKEW {
+            return Some(Verdict::Reject(format!("timestamp is in the future")));
+        }
+        None
+    }
+
+    /// Compare the response of the node to the verdict. Returns a description
+    /// of the mismatch if the node did not respond as expected. Requests that
+    /// failed without a response are not compared.
+    pub fn check(&self, response: &Result<(String, u16), RestError>) -> Result<(), String> {
+        let (body, status) = match response {
+            Ok((body, status)) => (body, *status),
+            Err(_) => return Ok(()),
+        };
+        match self {
+            Verdict::Accept if (200..300).contains(&status) => Ok(()),
+            Verdict::Accept => Err(format!(
+                "expected accept, got code {} ({})",
+                status,
+                quote_body(body)
+            )),
+            Verdict::Reject(_) if (400..500).contains(&status) => Ok(()),
+            Verdict::Reject(reason) => Err(format!(
+                "expected reject ({}), got code {} ({})",
+                reason,
+                status,
+                quote_body(body)
+            )),
+        }
+    }
+}
+
+impl Display for Verdict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Accept => write!(f, "accept"),
+            Verdict::Reject(reason) => write!(f, "reject ({})", reason),
+        }
+    }
+}
+
+/// Returns the start of a response body on a single line
+fn quote_body(body: &str) -> String {
+    let body = body.trim().replace('\n', " ");
+    match body.char_indices().nth(MAX_QUOTED_BODY) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body,
+    }
+}
+
+// ========================================================================== //
+
+/// Model of the chains on the node, as far as the client knows them. The
+/// ledger is built from the transactions of a run and is used to derive the
+/// verdict that the node is expected to give on each new transaction.
+#[derive(Default)]
+pub struct Ledger {
+    /// Accepted transactions of each bike, in chain order
+    chains: HashMap<String, Vec<Transaction>>,
+    /// Hashes of all accepted transactions
+    seen: HashSet<Hash>,
+    /// Decoding mode of the parser of the node
+    decoding: Decoding,
+}
+
+impl Ledger {
+    pub fn new() -> Ledger {
+        Ledger::default()
+    }
+
+    /// Create a ledger for a node that parses transactions in a decoding mode
+    pub fn with_decoding(decoding: Decoding) -> Ledger {
+        Ledger {
+            decoding,
+            ..Ledger::default()
+        }
+    }
+
+    /// Returns the verdict that the node is expected to give on a transaction
+    pub fn expect(&self, tx: &Transaction) -> Verdict {
+        if let Some(verdict) = Verdict::of_content(tx) {
+            return verdict;
+        }
+        if self.seen.contains(&tx.calc_hash()) {
+            return Verdict::Reject(format!("transaction was already accepted"));
+        }
+
+        let chain = self.chains.get(tx.get_id());
+        if !tx.has_input() {
+            return match chain {
+                Some(_) => Verdict::Reject(format!("bike is already registered")),
+                None => Verdict::Accept,
+            };
+        }
+        let chain = match chain {
+            Some(chain) => chain,
+            None => return Verdict::Reject(format!("bike is not registered")),
+        };
+        if tx.verify_is_next(chain.last().unwrap()) {
+            Verdict::Accept
+        } else if chain.iter().any(|prev| tx.verify_is_next(prev)) {
+            Verdict::Reject(format!("bike was already transferred by its owner"))
+        } else {
+            Verdict::Reject(format!("input is not the key of the owner"))
+        }
+    }
+
+    /// Derive the verdict on the body of a job. A transaction that the node
+    /// is expected to accept is added to the ledger right away, so that the
+    /// transactions after it are judged against it.
+    pub fn judge(&mut self, job: &Job) -> Verdict {
+        let tx = match Transaction::from_json_with(&job.body(), self.decoding) {
+            Ok(tx) => tx,
+            Err(e) => return Verdict::Reject(format!("malformed transaction ({})", e)),
+        };
+        let verdict = self.expect(&tx);
+        if verdict == Verdict::Accept {
+            self.apply(tx);
+        }
+        verdict
+    }
+
+    /// Update the ledger with the response of the node, so that it follows
+    /// the node also when the node did not give the expected verdict
+    pub fn settle(&mut self, job: &Job, verdict: &Verdict, accepted: bool) {
+        let tx = match Transaction::from_json_with(&job.body(), self.decoding) {
+            Ok(tx) => tx,
+            Err(_) => return,
+        };
+        match (verdict, accepted) {
+            (Verdict::Accept, false) => self.revert(&tx),
+            (Verdict::Reject(_), true) => self.apply(tx),
+            _ => {}
+        }
+    }
+
+    /// Returns the accepted transactions of a bike, in chain order
+    pub fn chain(&self, id: &str) -> &[Transaction] {
+        match self.chains.get(id) {
+            Some(chain) => chain,
+            None => &[],
+        }
+    }
+
+    /// Returns the accepted transaction with a hash
+    pub fn find(&self, hash: &Hash) -> Option<&Transaction> {
+        if !self.seen.contains(hash) {
+            return None;
+        }
+        self.transactions().find(|tx| &tx.calc_hash() == hash)
+    }
+
+    /// Returns every accepted transaction, bike by bike in chain order
+    pub fn transactions(&self) -> impl Iterator<Item = &Transaction> {
+        self.chains.values().flat_map(|chain| chain.iter())
+    }
+
+    /// Add an accepted transaction to the chain of its bike
+    pub fn apply(&mut self, tx: Transaction) {
+        self.seen.insert(tx.calc_hash());
+        self.chains
+            .entry(tx.get_id().clone())
+            .or_insert_with(Vec::new)
+            .push(tx);
+    }
+
+    /// Remove a transaction that the node did not accept from the chain of
+    /// its bike
+    fn revert(&mut self, tx: &Transaction) {
+        let hash = tx.calc_hash();
+        if !self.seen.remove(&hash) {
+            return;
+        }
+        if let Some(chain) = self.chains.get_mut(tx.get_id()) {
+            chain.retain(|t| t.calc_hash() != hash);
+            if chain.is_empty() {
+                self.chains.remove(tx.get_id());
+            }
+        }
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attack::{Attack, ATTACKS};
+    use crate::entropy::Entropy;
+    use crate::transaction::SigningScheme;
+
+    #[test]
+    fn test_attacks_are_rejected() {
+        let mut entropy = Entropy::default();
+        for attack in ATTACKS.iter() {
+            let mut ledger = Ledger::new();
+            let jobs = attack.make_jobs(format!("SN1337BIKE"), SigningScheme::V1, &mut entropy);
+            let (last, setup) = jobs.split_last().unwrap();
+            for job in setup {
+                assert_eq!(ledger.judge(job), Verdict::Accept, "{}", attack);
+            }
+            assert!(ledger.judge(last) != Verdict::Accept, "{}", attack);
+        }
+
+        // Only the follow-up of a double-spend identifies it as such
+        let id = format!("SN1337BIKE");
+        let jobs = Attack::DoubleSpend.make_jobs(id, SigningScheme::Legacy, &mut entropy);
+        let mut ledger = Ledger::new();
+        ledger.judge(&jobs[0]);
+        ledger.judge(&jobs[1]);
+        assert_eq!(
+            ledger.judge(&jobs[2]),
+            Verdict::Reject(format!("bike was already transferred by its owner"))
+        );
+    }
+
+    #[test]
+    fn test_settle_follows_node() {
+        let (t0, sk0) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let (t1, _) = Transaction::debug_make_transfer(&t0, &sk0);
+        let register = Job::new("register", t0, None);
+        let transfer = Job::new("transfer", t1, None);
+
+        // The transfer depends on the registration that the node rejected
+        let mut ledger = Ledger::new();
+        let verdict = ledger.judge(&register);
+        ledger.settle(&register, &verdict, false);
+        assert_eq!(
+            ledger.judge(&transfer),
+            Verdict::Reject(format!("bike is not registered"))
+        );
+
+        let ok = Ok((String::new(), 200));
+        let bad = Ok((String::from("invalid"), 400));
+        assert_eq!(Verdict::Accept.check(&ok), Ok(()));
+        assert!(Verdict::Accept.check(&bad).is_err());
+        assert!(verdict
+            .check(&Err(RestError::Timeout(String::new())))
+            .is_ok());
+        assert!(Verdict::Reject(String::new()).check(&ok).is_err());
+    }
+}
@@ -39,6 +39,28 @@ pub fn hash_to_str(hash: &Hash) -> String {
     parts.join("")
 }
 
+/// Convert a string of hex digits to a hash value
+///
+pub fn str_to_hash(s: &str) -> Option<Hash> {
+    if s.len() != 2 * EMPTY_HASH.len() {
+        return None;
+    }
+    let mut hash = EMPTY_HASH;
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = hex_byte(s.get(2 * i..2 * i + 2)?)?;
+    }
+    Some(hash)
+}
+
+/// Parse a byte from two hex digits. Unlike u8::from_str_radix, this does not
+/// accept a sign in place of a digit.
+pub fn hex_byte(s: &str) -> Option<u8> {
+    match s.len() == 2 && s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        true => u8::from_str_radix(s, 16).ok(),
+        false => None,
+    }
+}
+
 // ========================================================================== //
 
 impl Hashable for String {
@@ -52,3 +74,20 @@ impl Hashable for &str {
         obj_hash(&self)
     }
 }
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        let hash = "hash".calc_hash();
+        assert_eq!(str_to_hash(&hash_to_str(&hash)), Some(hash));
+        assert_eq!(hex_byte("fF"), Some(0xff));
+        assert_eq!(hex_byte("+f"), None);
+        assert_eq!(hex_byte("f"), None);
+        let signed = format!("+f{}", &hash_to_str(&hash)[2..]);
+        assert_eq!(str_to_hash(&signed), None);
+    }
+}
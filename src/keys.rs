@@ -0,0 +1,308 @@
+use crate::hash;
+use base64::{decode_config, encode_config, DecodeError};
+use rust_sodium::crypto::sign::ed25519;
+use serde::de::{self, Deserialize, Deserializer, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::fmt::{self, Display, Formatter};
+use std::hash::{Hash, Hasher};
+
+// ========================================================================== //
+
+/// Number of bytes in a public key
+pub const PUBKEY_LEN: usize = 32;
+
+/// Number of bytes in a signature
+pub const SIGNATURE_LEN: usize = 64;
+
+// ========================================================================== //
+
+/// Errors from decoding keys and signatures
+#[derive(Clone, Debug, PartialEq)]
+pub enum KeyError {
+    /// The decoded value does not have the expected number of bytes
+    Length {
+        /// Expected number of bytes
+        expected: usize,
+        /// Number of bytes that were decoded
+        found: usize,
+    },
+    /// The text is not valid base64
+    Base64(DecodeError),
+    /// The text is not valid hex
+    Hex(String),
+}
+
+impl Display for KeyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyError::Length { expected, found } => {
+                write!(f, "expected {} bytes, found {}", expected, found)
+            }
+            KeyError::Base64(e) => write!(f, "invalid base64 ({})", e),
+            KeyError::Hex(e) => write!(f, "invalid hex ({})", e),
+        }
+    }
+}
+
+// ========================================================================== //
+
+/// Define a type that wraps a fixed number of bytes, with length-checked
+/// decoding, base64 and hex codecs and serde support. Values are serialized as
+/// URL-safe base64 in human-readable formats and as bytes otherwise.
+macro_rules! fixed_bytes {
+    ($(#[$attr:meta])* $name:ident, $len:expr, $what:expr) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy)]
+        pub struct $name([u8; $len]);
+
+        impl $name {
+            /// Create a value from a slice with exactly the right number of
+            /// bytes
+            pub fn from_slice(bytes: &[u8]) -> Result<$name, KeyError> {
+                if bytes.len() != $len {
+                    return Err(KeyError::Length {
+                        expected: $len,
+                        found: bytes.len(),
+                    });
+                }
+                let mut v = [0; $len];
+                v.copy_from_slice(bytes);
+                Ok($name(v))
+            }
+
+            /// Decode a value from URL-safe base64
+            pub fn from_base64(s: &str) -> Result<$name, KeyError> {
+                match decode_config(s, base64::URL_SAFE) {
+                    Ok(bytes) => $name::from_slice(&bytes),
+                    Err(e) => Err(KeyError::Base64(e)),
+                }
+            }
+
+            /// Decode a value from base64 with the URL-safe or the standard
+            /// alphabet, with or without padding, or from hex. Returns the
+            /// error of the URL-safe decoding if none of them fits.
+            pub fn decode_lenient(s: &str) -> Result<$name, KeyError> {
+                let configs = [
+                    base64::URL_SAFE,
+                    base64::STANDARD,
+                    base64::URL_SAFE_NO_PAD,
+                    base64::STANDARD_NO_PAD,
+                ];
+                for config in configs.iter() {
+                    let bytes = decode_config(s, *config).ok();
+                    if let Some(v) = bytes.and_then(|b| $name::from_slice(&b).ok()) {
+                        return Ok(v);
+                    }
+                }
+                $name::from_hex(s).or_else(|_| $name::from_base64(s))
+            }
+
+            /// Decode a value from hex
+            pub fn from_hex(s: &str) -> Result<$name, KeyError> {
+                if s.len() % 2 != 0 {
+                    return Err(KeyError::Hex(format!("odd number of digits")));
+                }
+                let mut bytes = Vec::with_capacity(s.len() / 2);
+                for i in (0..s.len()).step_by(2) {
+                    let byte = s.get(i..i + 2).and_then(hash::hex_byte);
+                    match byte {
+                        Some(byte) => bytes.push(byte),
+                        None => return Err(KeyError::Hex(format!("invalid digit at {}", i))),
+                    }
+                }
+                $name::from_slice(&bytes)
+            }
+
+            /// Encode the value as URL-safe base64
+            pub fn to_base64(self) -> String {
+                encode_config(&self.0[..], base64::URL_SAFE)
+            }
+
+            /// Encode the value as hex
+            pub fn to_hex(self) -> String {
+                let parts: Vec<String> = self.0.iter().map(|b| format!("{:02x}", b)).collect();
+                parts.join("")
+            }
+
+            /// Returns the bytes of the value
+            pub fn as_bytes(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                &self.0
+            }
+        }
+
+        impl AsMut<[u8]> for $name {
+            fn as_mut(&mut self) -> &mut [u8] {
+                &mut self.0
+            }
+        }
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &$name) -> bool {
+                self.0[..] == other.0[..]
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0[..].hash(state)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                write!(f, "{}({})", stringify!($name), self.to_hex())
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.to_hex())
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                match serializer.is_human_readable() {
+                    true => serializer.serialize_str(&self.to_base64()),
+                    false => serializer.serialize_bytes(&self.0),
+                }
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<$name, D::Error> {
+                struct BytesVisitor;
+
+                impl<'de> Visitor<'de> for BytesVisitor {
+                    type Value = $name;
+
+                    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                        write!(f, "a {} of {} bytes", $what, $len)
+                    }
+
+                    fn visit_str<E: de::Error>(self, s: &str) -> Result<$name, E> {
+                        $name::from_base64(s).map_err(E::custom)
+                    }
+
+                    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<$name, E> {
+                        $name::from_slice(bytes).map_err(E::custom)
+                    }
+
+                    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<$name, A::Error> {
+                        let mut bytes = Vec::with_capacity($len);
+                        while let Some(byte) = seq.next_element::<u8>()? {
+                            bytes.push(byte);
+                        }
+                        $name::from_slice(&bytes).map_err(de::Error::custom)
+                    }
+                }
+
+                match deserializer.is_human_readable() {
+                    true => deserializer.deserialize_str(BytesVisitor),
+                    false => deserializer.deserialize_bytes(BytesVisitor),
+                }
+            }
+        }
+    };
+}
+
+fixed_bytes!(
+    /// Ed25519 public key
+    PubKey,
+    PUBKEY_LEN,
+    "public key"
+);
+
+fixed_bytes!(
+    /// Ed25519 signature
+    Signature,
+    SIGNATURE_LEN,
+    "signature"
+);
+
+impl PubKey {
+    /// Returns the key for use with sodium
+    pub fn to_sodium(self) -> ed25519::PublicKey {
+        ed25519::PublicKey(self.0)
+    }
+}
+
+impl From<&ed25519::PublicKey> for PubKey {
+    fn from(pk: &ed25519::PublicKey) -> PubKey {
+        PubKey(pk.0)
+    }
+}
+
+impl Signature {
+    /// Returns the signature for use with sodium
+    pub fn to_sodium(self) -> ed25519::Signature {
+        ed25519::Signature(self.0)
+    }
+}
+
+impl From<&ed25519::Signature> for Signature {
+    fn from(sig: &ed25519::Signature) -> Signature {
+        Signature(sig.0)
+    }
+}
+
+/// The signature of a transaction that has not been signed yet
+impl Default for Signature {
+    fn default() -> Signature {
+        Signature([0; SIGNATURE_LEN])
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_codecs() {
+        let pk = PubKey::from_slice(&[7; PUBKEY_LEN]).unwrap();
+        assert_eq!(PubKey::from_base64(&pk.to_base64()), Ok(pk));
+        assert_eq!(PubKey::from_hex(&pk.to_hex()), Ok(pk));
+        assert_eq!(
+            PubKey::from_slice(&[7; 16]),
+            Err(KeyError::Length {
+                expected: PUBKEY_LEN,
+                found: 16
+            })
+        );
+        assert!(PubKey::from_hex("0g").is_err());
+        let signed = format!("+f{}", &pk.to_hex()[2..]);
+        assert_eq!(
+            PubKey::from_hex(&signed),
+            Err(KeyError::Hex(String::from("invalid digit at 0")))
+        );
+
+        // Lenient decoding accepts other alphabets, missing padding and hex
+        let key = PubKey::from_slice(&[0xfb; PUBKEY_LEN]).unwrap();
+        let standard = encode_config(key.as_bytes(), base64::STANDARD);
+        assert!(PubKey::from_base64(&standard).is_err());
+        assert_eq!(PubKey::decode_lenient(&standard), Ok(key));
+        assert_eq!(
+            PubKey::decode_lenient(standard.trim_end_matches('=')),
+            Ok(key)
+        );
+        assert_eq!(PubKey::decode_lenient(&key.to_hex()), Ok(key));
+        assert!(PubKey::decode_lenient("+-").is_err());
+
+        // JSON holds base64 and bincode holds the raw bytes
+        let sig = Signature::from_slice(&[1; SIGNATURE_LEN]).unwrap();
+        let json = serde_json::to_string(&sig).unwrap();
+        assert_eq!(json, format!("\"{}\"", sig.to_base64()));
+        assert_eq!(serde_json::from_str::<Signature>(&json).unwrap(), sig);
+        let bin = bincode::serialize(&sig).unwrap();
+        assert_eq!(bincode::deserialize::<Signature>(&bin).unwrap(), sig);
+    }
+}
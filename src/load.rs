@@ -0,0 +1,351 @@
+use crate::expect::Ledger;
+use crate::profile::RateProfile;
+use crate::rest::{RestClient, RestError};
+use crate::targets::Targets;
+use crate::transaction::Decoding;
+use crate::workload::{Job, NextJob, Workload};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// ========================================================================== //
+
+/// Default number of concurrent workers
+pub const DEFAULT_WORKERS: usize = 4;
+
+/// Maximum number of queued transactions in an open-loop run. The feeder only
+/// blocks on the workers when this many transactions are waiting to be sent.
+const OPEN_LOOP_QUEUE: usize = 10_000;
+
+/// Longest time the feeder sleeps before checking whether it should stop
+const MAX_SLEEP: Duration = Duration::from_millis(100);
+
+/// Time the feeder waits before asking a pending workload again
+const PENDING_SLEEP: Duration = Duration::from_millis(1);
+
+// ========================================================================== //
+
+/// Configuration of a load run
+#[derive(Clone, Debug)]
+pub struct LoadConfig {
+    /// Transaction endpoints of the nodes
+    pub targets: Targets,
+    /// Client that sends the requests
+    pub client: RestClient,
+    /// Number of concurrent workers that send transactions
+    pub workers: usize,
+    /// Rate profile for an open-loop run. When this is None, transactions are
+    /// sent back-to-back as fast as the workers can.
+    pub profile: Option<RateProfile>,
+    /// Longest duration of a run without a rate profile
+    pub duration: Option<Duration>,
+    /// Decoding mode of the parser of the nodes, which the expected verdicts
+    /// are derived with
+    pub decoding: Decoding,
+}
+
+/// Result of sending a single transaction
+pub struct SendResult {
+    /// Job that was sent
+    pub job: Job,
+    /// URL the transaction was sent to
+    pub url: String,
+    /// Time when the request was sent
+    pub sent_at: Instant,
+    /// Response body and status code, or the error if the request failed
+    pub response: Result<(String, u16), RestError>,
+    /// Time from the scheduled send time of the job until the response was
+    /// received. For a job without a scheduled time, this is the time from
+    /// sending the request.
+    pub latency: Duration,
+    /// Time from the scheduled send time of the job until the request was
+    /// sent, which the job spent waiting for a worker
+    pub lag: Duration,
+    /// Description of how the response differs from the expected verdict
+    pub mismatch: Option<String>,
+}
+
+impl SendResult {
+    /// Returns whether the node accepted the transaction
+    pub fn is_accepted(&self) -> bool {
+        match self.response {
+            Ok((_, status)) => (200..300).contains(&status),
+            Err(_) => false,
+        }
+    }
+
+    /// Compare the response to the verdict that was expected for the job
+    pub fn check_expected(&mut self) {
+        if let Some(ref expected) = self.job.expected {
+            self.mismatch = expected.check(&self.response).err();
+        }
+    }
+
+    /// Create the result of a job that was sent to a local node just now and
+    /// got a response after a latency, without a lag or a mismatch
+    #[cfg(test)]
+    pub fn debug_new(
+        job: Job,
+        response: Result<(String, u16), RestError>,
+        latency: Duration,
+    ) -> SendResult {
+        SendResult {
+            job,
+            url: String::from("http://localhost:8000/transaction"),
+            sent_at: Instant::now(),
+            response,
+            latency,
+            lag: Duration::from_secs(0),
+            mismatch: None,
+        }
+    }
+}
+
+/// Events that are streamed from a running load engine
+pub enum LoadEvent {
+    /// A transaction has been sent
+    Sent(SendResult),
+    /// All workers have finished
+    Done,
+}
+
+// ========================================================================== //
+
+/// Handle to a running load engine
+pub struct LoadHandle {
+    /// Events from the workers
+    events: Receiver<LoadEvent>,
+    /// Flag that is set to stop the run
+    stop: Arc<AtomicBool>,
+}
+
+impl LoadHandle {
+    /// Returns the next event if one is available, without blocking
+    pub fn try_recv(&self) -> Option<LoadEvent> {
+        match self.events.try_recv() {
+            Ok(e) => Some(e),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => None,
+        }
+    }
+
+    /// Returns the next event, blocking until one is available. Returns None
+    /// once the engine has shut down.
+    pub fn recv(&self) -> Option<LoadEvent> {
+        self.events.recv().ok()
+    }
+
+    /// Request the run to stop. Transactions that are already in flight will
+    /// still be reported.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+// ========================================================================== //
+
+/// Start a load run. Transactions are taken from the workload by a feeder
+/// thread and sent by a pool of workers. If the configuration has a rate
+/// profile, the feeder queues transactions on the schedule of the profile
+/// instead of as soon as a worker is available. Results are streamed back
+/// through the returned handle. Each job is judged against a ledger of the
+/// run, and the response of the node is compared to the expected verdict.
+pub fn start(config: LoadConfig, workload: Box<dyn Workload>) -> LoadHandle {
+    let workers = config.workers.max(1);
+    let workload = Arc::new(Mutex::new(workload));
+    let ledger = Arc::new(Mutex::new(Ledger::with_decoding(config.decoding)));
+    let stop = Arc::new(AtomicBool::new(false));
+    let (event_tx, event_rx) = mpsc::channel();
+    let queue = match config.profile {
+        Some(_) => OPEN_LOOP_QUEUE,
+        None => workers,
+    };
+    let (job_tx, job_rx) = mpsc::sync_channel(queue);
+    let job_rx = Arc::new(Mutex::new(job_rx));
+
+    // Feeder
+    {
+        let source = Source {
+            workload: workload.clone(),
+            ledger: ledger.clone(),
+            stop: stop.clone(),
+        };
+        let config = config.clone();
+        thread::spawn(move || match config.profile {
+            Some(ref profile) => feed_scheduled(profile, &source, &job_tx),
+            None => feed(config.duration, &source, &job_tx),
+        });
+    }
+
+    // Workers
+    let active = Arc::new(AtomicUsize::new(workers));
+    let sequence = Arc::new(AtomicUsize::new(0));
+    for _ in 0..workers {
+        let config = config.clone();
+        let shared = Shared {
+            workload: workload.clone(),
+            ledger: ledger.clone(),
+            sequence: sequence.clone(),
+        };
+        let job_rx = job_rx.clone();
+        let event_tx = event_tx.clone();
+        let active = active.clone();
+        thread::spawn(move || {
+            work(&config, &shared, &job_rx, &event_tx);
+            if active.fetch_sub(1, Ordering::SeqCst) == 1 {
+                let _ = event_tx.send(LoadEvent::Done);
+            }
+        });
+    }
+
+    LoadHandle {
+        events: event_rx,
+        stop,
+    }
+}
+
+// ========================================================================== //
+
+/// Source of the jobs that the feeder queues
+struct Source {
+    /// Workload that generates the jobs
+    workload: Arc<Mutex<Box<dyn Workload>>>,
+    /// Ledger that the jobs are judged against
+    ledger: Arc<Mutex<Ledger>>,
+    /// Flag that is set to stop the run
+    stop: Arc<AtomicBool>,
+}
+
+/// Take jobs from the workload and queue them for the workers, until the
+/// workload is exhausted or the duration has passed
+fn feed(duration: Option<Duration>, source: &Source, jobs: &SyncSender<Job>) {
+    let start = Instant::now();
+    while let Some(job) = next_job(source) {
+        if jobs.send(job).is_err() {
+            break;
+        }
+        if let Some(duration) = duration {
+            if start.elapsed() >= duration {
+                break;
+            }
+        }
+    }
+}
+
+/// Take jobs from the workload and queue them at the times scheduled by the
+/// rate profile
+fn feed_scheduled(profile: &RateProfile, source: &Source, jobs: &SyncSender<Job>) {
+    let start = Instant::now();
+    let mut n = 0;
+    while let Some(at) = profile.send_time(n) {
+        // Wait until the scheduled time
+        loop {
+            if source.stop.load(Ordering::SeqCst) {
+                return;
+            }
+            let elapsed = start.elapsed();
+            if elapsed >= at {
+                break;
+            }
+            thread::sleep((at - elapsed).min(MAX_SLEEP));
+        }
+
+        let mut job = match next_job(source) {
+            Some(job) => job,
+            None => break,
+        };
+        job.scheduled = Some(start + at);
+        if jobs.send(job).is_err() {
+            break;
+        }
+        n += 1;
+    }
+}
+
+/// Returns the next job of the workload with its expected verdict, waiting
+/// while the workload is pending. Returns None when the workload is exhausted
+/// or the run is stopped.
+fn next_job(source: &Source) -> Option<Job> {
+    while !source.stop.load(Ordering::SeqCst) {
+        match source.workload.lock().unwrap().next_job() {
+            NextJob::Ready(mut job) => {
+                job.expected = Some(source.ledger.lock().unwrap().judge(&job));
+                return Some(job);
+            }
+            NextJob::Pending => {}
+            NextJob::Done => return None,
+        }
+        thread::sleep(PENDING_SLEEP);
+    }
+    None
+}
+
+/// State that the workers share
+struct Shared {
+    /// Workload that is told about the results of its jobs
+    workload: Arc<Mutex<Box<dyn Workload>>>,
+    /// Ledger that follows the verdicts of the nodes
+    ledger: Arc<Mutex<Ledger>>,
+    /// Number of jobs that have been taken from the queue
+    sequence: Arc<AtomicUsize>,
+}
+
+/// Send queued jobs until the queue is closed. Each job is sent to the
+/// targets that the strategy picks for it. With a broadcast, the job only
+/// counts as accepted if all nodes accepted it. The latency of a scheduled job
+/// is measured from its scheduled time, so that the time it waited in the
+/// queue of an overloaded run is not left out.
+fn work(
+    config: &LoadConfig,
+    shared: &Shared,
+    jobs: &Mutex<Receiver<Job>>,
+    events: &Sender<LoadEvent>,
+) {
+    loop {
+        // The sequence number is taken with the job, so that it follows the
+        // order of the queue
+        let (job, seq) = {
+            let jobs = jobs.lock().unwrap();
+            match jobs.recv() {
+                Ok(job) => (job, shared.sequence.fetch_add(1, Ordering::SeqCst)),
+                Err(_) => break,
+            }
+        };
+        let mut results = Vec::new();
+        for target in config.targets.pick(seq, job.tx.get_id()) {
+            let (body, format) = job.encode(target.format);
+            let sent_at = Instant::now();
+            let start = job.scheduled.map(|at| at.min(sent_at)).unwrap_or(sent_at);
+            let response = config.client.post(target.url, &body, format.content_type());
+            let mut result = SendResult {
+                job: job.clone(),
+                url: String::from(target.url),
+                sent_at,
+                response,
+                latency: start.elapsed(),
+                lag: sent_at - start,
+                mismatch: None,
+            };
+            result.check_expected();
+            results.push(result);
+        }
+
+        let accepted = results.iter().all(|r| r.is_accepted());
+        let responded = results.iter().any(|r| r.response.is_ok());
+        if let (Some(ref expected), true) = (&job.expected, responded) {
+            shared
+                .ledger
+                .lock()
+                .unwrap()
+                .settle(&job, expected, accepted);
+        }
+        shared.workload.lock().unwrap().on_result(&job, accepted);
+        for result in results {
+            if events.send(LoadEvent::Sent(result)).is_err() {
+                return;
+            }
+        }
+    }
+}
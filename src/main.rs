@@ -1,11 +1,40 @@
 mod app;
+mod attack;
+mod block;
+mod chain;
+mod cli;
+mod confirm;
+mod diagnose;
+mod entropy;
+mod expect;
 mod hash;
+mod keys;
+mod load;
+mod merkle;
+mod mine;
+mod mock;
+mod names;
+mod profile;
+mod query;
 mod rest;
+mod scenario;
+mod session;
+mod stats;
+mod targets;
 mod transaction;
+mod wallet;
+mod wire;
+mod workload;
 
 // ========================================================================== //
 
 fn main() {
+    // Run headless when a command is given
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        std::process::exit(cli::run(&args));
+    }
+
     let app = match app::App::new("Simulation Client") {
         Ok(a) => a,
         Err(_) => panic!("Failed to create application"),
@@ -0,0 +1,334 @@
+use crate::hash::{self, Hash, EMPTY_HASH};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+// ========================================================================== //
+
+/// Side of the sibling of a node in a Merkle proof
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Side {
+    /// The sibling is hashed before the node
+    Left,
+    /// The sibling is hashed after the node
+    Right,
+}
+
+/// Step of a Merkle proof, from a node to its parent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProofStep {
+    /// Hash of the sibling of the node
+    pub sibling: Hash,
+    /// Side of the sibling
+    pub side: Side,
+}
+
+/// Proof that a leaf is included in a Merkle tree, as the siblings on the
+/// path from the leaf to the root
+#[derive(Clone, Debug, PartialEq)]
+pub struct MerkleProof {
+    /// Index of the leaf
+    pub index: usize,
+    /// Number of leaves of the tree
+    pub count: usize,
+    /// Steps from the leaf to the root
+    pub steps: Vec<ProofStep>,
+}
+
+impl MerkleProof {
+    /// Returns the root that the proof leads to from a leaf
+    pub fn root_of(&self, leaf: &Hash) -> Hash {
+        self.steps
+            .iter()
+            .fold(hash_leaf(leaf), |node, step| match step.side {
+                Side::Left => hash_pair(&step.sibling, &node),
+                Side::Right => hash_pair(&node, &step.sibling),
+            })
+    }
+
+    /// Returns whether the proof shows that a leaf is included in the tree
+    /// with a root, at the index of the proof. The sides of the steps must be
+    /// those of the path from that index in a tree with that many leaves.
+    pub fn verify(&self, leaf: &Hash, root: &Hash) -> bool {
+        if self.index >= self.count {
+            return false;
+        }
+        let sides = path(self.index, self.count);
+        sides.len() == self.steps.len()
+            && sides
+                .iter()
+                .zip(&self.steps)
+                .all(|(side, step)| *side == step.side)
+            && &self.root_of(leaf) == root
+    }
+
+    /// Returns the proof as JSON, with the siblings as hex hashes
+    pub fn to_json(&self) -> String {
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| StepJson {
+                sibling: hash::hash_to_str(&step.sibling),
+                side: step.side,
+            })
+            .collect();
+        let proof = ProofJson {
+            index: self.index,
+            count: self.count,
+            steps,
+        };
+        serde_json::to_string(&proof).expect("Failed to convert proof to JSON")
+    }
+
+    /// Parse a proof from JSON
+    pub fn from_json(json: &str) -> Result<MerkleProof, String> {
+        let proof: ProofJson = match serde_json::from_str(json) {
+            Ok(proof) => proof,
+            Err(e) => return Err(format!("Invalid proof ({})", e)),
+        };
+        let mut steps = Vec::with_capacity(proof.steps.len());
+        for (i, step) in proof.steps.into_iter().enumerate() {
+            match hash::str_to_hash(&step.sibling) {
+                Some(sibling) => steps.push(ProofStep {
+                    sibling,
+                    side: step.side,
+                }),
+                None => return Err(format!("Invalid proof (sibling {} is not a hash)", i)),
+            }
+        }
+        Ok(MerkleProof {
+            index: proof.index,
+            count: proof.count,
+            steps,
+        })
+    }
+}
+
+/// Merkle proof in JSON
+#[derive(Serialize, Deserialize)]
+struct ProofJson {
+    index: usize,
+    count: usize,
+    steps: Vec<StepJson>,
+}
+
+/// Step of a Merkle proof in JSON
+#[derive(Serialize, Deserialize)]
+struct StepJson {
+    sibling: String,
+    side: Side,
+}
+
+impl Display for MerkleProof {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MerkleProof:{{ index: {}, count: {}, steps: [",
+            self.index, self.count
+        )?;
+        for (i, step) in self.steps.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ", " };
+            let side = match step.side {
+                Side::Left => "left",
+                Side::Right => "right",
+            };
+            write!(f, "{}{} {}", sep, side, hash::hash_to_str(&step.sibling))?;
+        }
+        write!(f, "] }}")
+    }
+}
+
+// ========================================================================== //
+
+/// Tag hashed before a leaf
+const LEAF_TAG: u8 = 0x00;
+
+/// Tag hashed before the children of an inner node
+const NODE_TAG: u8 = 0x01;
+
+/// Returns the hash of a leaf
+fn hash_leaf(leaf: &Hash) -> Hash {
+    let mut buf = Vec::with_capacity(1 + leaf.len());
+    buf.push(LEAF_TAG);
+    buf.extend_from_slice(leaf);
+    hash::obj_hash(&buf)
+}
+
+/// Returns the hash of an inner node from the hashes of its children
+fn hash_pair(left: &Hash, right: &Hash) -> Hash {
+    let mut buf = Vec::with_capacity(1 + 2 * left.len());
+    buf.push(NODE_TAG);
+    buf.extend_from_slice(left);
+    buf.extend_from_slice(right);
+    hash::obj_hash(&buf)
+}
+
+/// Returns the sides of the siblings on the path from the leaf at an index to
+/// the root of a tree with a number of leaves. A node without a sibling has no
+/// step on the path.
+fn path(index: usize, count: usize) -> Vec<Side> {
+    let mut sides = Vec::new();
+    let (mut i, mut n) = (index, count);
+    while n > 1 {
+        if i % 2 == 1 {
+            sides.push(Side::Left);
+        } else if i + 1 < n {
+            sides.push(Side::Right);
+        }
+        i /= 2;
+        n -= n / 2;
+    }
+    sides
+}
+
+/// Binary hash tree over a list of leaves, built by the rules of the node.
+/// A leaf is hashed after the tag 0x00, and the parent of two nodes is the
+/// hash of the tag 0x01 followed by their concatenation, so that a leaf can
+/// never pass for an inner node. A node without a sibling is moved up to the
+/// next level as it is, so that no leaf is ever hashed twice.
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    /// Leaves of the tree
+    leaves: Vec<Hash>,
+    /// Levels of the tree, from the hashes of the leaves to the root
+    levels: Vec<Vec<Hash>>,
+}
+
+impl MerkleTree {
+    /// Build the tree over a list of leaves
+    pub fn new(leaves: Vec<Hash>) -> MerkleTree {
+        let mut levels = vec![leaves.iter().map(hash_leaf).collect::<Vec<Hash>>()];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => hash_pair(left, right),
+                    _ => pair[0],
+                })
+                .collect();
+            levels.push(next);
+        }
+        MerkleTree { leaves, levels }
+    }
+
+    /// Returns the root of the tree, or EMPTY_HASH if there are no leaves
+    pub fn root(&self) -> Hash {
+        match self.levels.last().unwrap().first() {
+            Some(root) => *root,
+            None => EMPTY_HASH,
+        }
+    }
+
+    /// Returns the leaves of the tree
+    pub fn leaves(&self) -> &[Hash] {
+        &self.leaves
+    }
+
+    /// Returns the proof that the leaf at an index is included in the tree,
+    /// or None if there is no such leaf
+    pub fn proof(&self, index: usize) -> Option<MerkleProof> {
+        if index >= self.leaves.len() {
+            return None;
+        }
+        let mut steps = Vec::new();
+        let mut i = index;
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = match i % 2 {
+                0 => level.get(i + 1).map(|h| (*h, Side::Right)),
+                _ => Some((level[i - 1], Side::Left)),
+            };
+            if let Some((sibling, side)) = sibling {
+                steps.push(ProofStep { sibling, side });
+            }
+            i /= 2;
+        }
+        Some(MerkleProof {
+            index,
+            count: self.leaves.len(),
+            steps,
+        })
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::Hashable;
+
+    #[test]
+    fn test_merkle_proofs() {
+        assert_eq!(MerkleTree::new(Vec::new()).root(), EMPTY_HASH);
+        let leaf = "only".calc_hash();
+        assert_eq!(MerkleTree::new(vec![leaf]).root(), hash_leaf(&leaf));
+
+        for count in 1..10 {
+            let leaves: Vec<Hash> = (0..count).map(|i| i.to_string().calc_hash()).collect();
+            let tree = MerkleTree::new(leaves.clone());
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = tree.proof(i).unwrap();
+                assert!(proof.verify(leaf, &tree.root()), "{} of {}", i, count);
+                assert!(!proof.verify(&"other".calc_hash(), &tree.root()));
+            }
+            assert!(tree.proof(count).is_none());
+        }
+
+        let leaves: Vec<Hash> = ["a", "b", "c"].iter().map(|s| s.calc_hash()).collect();
+        let tree = MerkleTree::new(leaves.clone());
+        let hashes: Vec<Hash> = leaves.iter().map(hash_leaf).collect();
+        let ab = hash_pair(&hashes[0], &hashes[1]);
+        assert_eq!(tree.root(), hash_pair(&ab, &hashes[2]));
+        let proof = tree.proof(2).unwrap();
+        assert_eq!(
+            proof.steps,
+            vec![ProofStep {
+                sibling: ab,
+                side: Side::Left,
+            }]
+        );
+        assert!(!proof.verify(&leaves[2], &ab));
+        assert_eq!(MerkleProof::from_json(&proof.to_json()).unwrap(), proof);
+    }
+
+    #[test]
+    fn test_forged_proofs() {
+        let leaves: Vec<Hash> = ["a", "b", "c", "d"].iter().map(|s| s.calc_hash()).collect();
+        let tree = MerkleTree::new(leaves.clone());
+        let hashes: Vec<Hash> = leaves.iter().map(hash_leaf).collect();
+        let ab = hash_pair(&hashes[0], &hashes[1]);
+        let cd = hash_pair(&hashes[2], &hashes[3]);
+
+        // An inner node does not pass for a leaf with the rest of its path
+        let inner = MerkleProof {
+            index: 0,
+            count: 2,
+            steps: vec![ProofStep {
+                sibling: cd,
+                side: Side::Right,
+            }],
+        };
+        assert!(!inner.verify(&ab, &tree.root()));
+
+        // The steps must follow the index and the number of leaves
+        let proof = tree.proof(2).unwrap();
+        assert!(proof.verify(&leaves[2], &tree.root()));
+        for (index, count) in [(3, 4), (1, 4), (2, 3), (2, 5), (4, 4)].iter() {
+            let moved = MerkleProof {
+                index: *index,
+                count: *count,
+                ..proof.clone()
+            };
+            assert!(
+                !moved.verify(&leaves[2], &tree.root()),
+                "{} of {}",
+                index,
+                count
+            );
+        }
+        let mut short = proof.clone();
+        short.steps.pop();
+        assert!(!short.verify(&leaves[2], &ab));
+    }
+}
@@ -0,0 +1,106 @@
+use crate::block::Block;
+use crate::hash::{Hash, Hashable};
+use crate::query;
+use crate::rest::{RestClient, RestError};
+
+// ========================================================================== //
+
+/// Default number of leading zero bits of the hash of a mined block
+pub const DEFAULT_DIFFICULTY: u32 = 16;
+
+/// Largest difficulty that is accepted. A block takes 2^32 hashes to mine on
+/// average at this difficulty, which is hours for a single thread.
+pub const MAX_DIFFICULTY: u32 = 32;
+
+/// Number of times the expected number of hashes that are tried before mining
+/// gives up, as a power of two
+const ATTEMPT_MARGIN_BITS: u32 = 4;
+
+/// Default number of transactions in a mined block
+pub const DEFAULT_BLOCK_SIZE: usize = 10;
+
+// ========================================================================== //
+
+/// Returns the number of leading zero bits of a hash
+pub fn leading_zeros(hash: &Hash) -> u32 {
+    let mut zeros = 0;
+    for byte in hash.iter() {
+        zeros += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    zeros
+}
+
+/// Returns whether a hash has at least 'difficulty' leading zero bits
+pub fn meets_difficulty(hash: &Hash, difficulty: u32) -> bool {
+    leading_zeros(hash) >= difficulty
+}
+
+/// Returns the number of hashes to try before giving up on mining a block,
+/// which is 16 times the expected number of hashes for the difficulty
+pub fn attempt_limit(difficulty: u32) -> u64 {
+    match difficulty + ATTEMPT_MARGIN_BITS {
+        bits if bits < 64 => 1 << bits,
+        _ => u64::max_value(),
+    }
+}
+
+/// Mine a block by trying nonces from 'start' until the hash of the block
+/// meets the difficulty. Returns the number of hashes that were calculated,
+/// or an error if none of 'limit' nonces gave a hash that meets it.
+pub fn mine(block: &mut Block, difficulty: u32, start: u64, limit: u64) -> Result<u64, String> {
+    let mut nonce = start;
+    for attempts in 1..=limit {
+        block.set_nonce(nonce);
+        if meets_difficulty(&block.calc_hash(), difficulty) {
+            return Ok(attempts);
+        }
+        nonce = nonce.wrapping_add(1);
+    }
+    Err(format!(
+        "No hash with {} leading zero bits in {} attempts",
+        difficulty, limit
+    ))
+}
+
+/// Submit a block to the block endpoint of the node of a transaction endpoint
+pub fn submit_block(
+    client: &RestClient,
+    endpoint: &str,
+    block: &Block,
+) -> Result<(String, u16), RestError> {
+    let url = query::block_url(endpoint);
+    client.post(&url, block.to_json().as_bytes(), "application/json")
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hash::EMPTY_HASH;
+    use crate::transaction::Transaction;
+
+    #[test]
+    fn test_mine() {
+        let mut hash = EMPTY_HASH;
+        assert_eq!(leading_zeros(&hash), 256);
+        hash[1] = 0x10;
+        assert_eq!(leading_zeros(&hash), 11);
+        assert!(meets_difficulty(&hash, 11));
+        assert!(!meets_difficulty(&hash, 12));
+
+        let (tx, _) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let mut block = Block::new(EMPTY_HASH, vec![tx]);
+        let attempts = mine(&mut block, 8, u64::max_value(), attempt_limit(8)).unwrap();
+        assert!(attempts >= 1);
+        assert!(meets_difficulty(&block.calc_hash(), 8));
+        assert_eq!(block.verify(), Ok(()));
+
+        // Mining gives up after the limit
+        assert!(mine(&mut block, 64, 0, 100).is_err());
+        assert_eq!(attempt_limit(16), 1 << 20);
+        assert_eq!(attempt_limit(256), u64::max_value());
+    }
+}
@@ -0,0 +1,683 @@
+use crate::block::Block;
+use crate::hash::{self, Hash, Hashable, EMPTY_HASH};
+use crate::mine;
+use crate::query::{self, Inclusion, BLOCK_PATH, CHAIN_PATH, STATUS, TIP};
+use crate::transaction::{self, Decoding, Transaction, TxError};
+use crate::wire::{self, Format};
+use serde_json::json;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// ========================================================================== //
+
+/// Default address of the mock node, which matches the default URL of the
+/// client
+pub const DEFAULT_LISTEN: &str = "127.0.0.1:8000";
+
+/// Path of the transaction endpoint
+const TRANSACTION_PATH: &str = "/transaction";
+
+/// Largest request body that the mock node reads
+const MAX_BODY: usize = 1 << 20;
+
+/// Number of seconds a timestamp may be ahead of the clock of the mock node
+const MAX_CLOCK_SKEW: u64 = 60;
+
+// ========================================================================== //
+
+/// Response of the mock node
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    /// HTTP status code
+    pub status: u16,
+    /// JSON body
+    pub body: String,
+}
+
+impl Response {
+    fn accepted(tx: &Transaction) -> Response {
+        Response {
+            status: 200,
+            body: json!({ "status": "accepted", "hash": hash::hash_to_str(&tx.calc_hash()) })
+                .to_string(),
+        }
+    }
+
+    fn invalid(e: &TxError) -> Response {
+        Response {
+            status: 400,
+            body: json!({
+                "status": "rejected",
+                "error": e.to_string(),
+                "kind": e.kind(),
+                "field": e.field(),
+            })
+            .to_string(),
+        }
+    }
+
+    fn found(body: String) -> Response {
+        Response { status: 200, body }
+    }
+
+    fn error(status: u16, msg: &str) -> Response {
+        Response {
+            status,
+            body: json!({ "status": "rejected", "error": msg }).to_string(),
+        }
+    }
+}
+
+/// Returns the reason phrase of a status code
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        _ => "Internal Server Error",
+    }
+}
+
+// ========================================================================== //
+
+/// Block that the mock node accepted
+struct StoredBlock {
+    block: Block,
+    /// Number of blocks from the first block to this one, counting both
+    height: u64,
+}
+
+/// Stand-in for a node that validates transactions like the node does and
+/// keeps the chain of every bike in memory. The rules are its own, apart from
+/// the model that the client uses to expect verdicts, so that a mistake in
+/// that model shows up as a mismatch.
+pub struct MockNode {
+    /// Accepted transactions of each bike, in chain order
+    chains: HashMap<String, Vec<Transaction>>,
+    /// Accepted transactions by hash
+    accepted: HashMap<Hash, Transaction>,
+    /// Decoding mode of JSON transactions
+    decoding: Decoding,
+    /// Number of leading zero bits that the hash of a block must have
+    difficulty: u32,
+    /// Accepted blocks by hash, which form a tree of branches
+    blocks: HashMap<Hash, StoredBlock>,
+    /// Hash of the block at the tip of the longest branch
+    tip: Option<Hash>,
+}
+
+impl MockNode {
+    pub fn new(decoding: Decoding, difficulty: u32) -> MockNode {
+        MockNode {
+            chains: HashMap::new(),
+            accepted: HashMap::new(),
+            decoding,
+            difficulty,
+            blocks: HashMap::new(),
+            tip: None,
+        }
+    }
+
+    /// Handle a request. Transactions are posted to the transaction endpoint,
+    /// and accepted transactions can be read back one by one below it or as
+    /// the chain of a bike. Blocks are posted to the block endpoint and read
+    /// back by hash below it.
+    pub fn handle(
+        &mut self,
+        method: &str,
+        path: &str,
+        content_type: Option<&str>,
+        body: &[u8],
+    ) -> Response {
+        let chain_prefix = format!("{}/", CHAIN_PATH);
+        let transaction_prefix = format!("{}/", TRANSACTION_PATH);
+        let block_prefix = format!("{}/", BLOCK_PATH);
+        match method {
+            "POST" if path == TRANSACTION_PATH => self.post_transaction(content_type, body),
+            "POST" if path == BLOCK_PATH => self.post_block(body),
+            "GET" if path.starts_with(&block_prefix) => self.get_block(&path[block_prefix.len()..]),
+            "GET" if path.starts_with(&chain_prefix) => self.get_chain(&path[chain_prefix.len()..]),
+            "GET" if path.starts_with(&transaction_prefix) => {
+                let rest = &path[transaction_prefix.len()..];
+                let status_suffix = format!("/{}", STATUS);
+                match rest.ends_with(&status_suffix) {
+                    true => self.get_status(&rest[..rest.len() - status_suffix.len()]),
+                    false => self.get_transaction(rest),
+                }
+            }
+            _ if path == TRANSACTION_PATH || path == BLOCK_PATH => {
+                Response::error(405, &format!("method {} is not allowed", method))
+            }
+            _ => Response::error(404, &format!("no such endpoint {}", path)),
+        }
+    }
+
+    /// Handle a posted transaction. The body is decoded in the format of its
+    /// content type, which defaults to JSON. Invalid transactions are rejected
+    /// with 400 and transactions that conflict with a chain with 409.
+    fn post_transaction(&mut self, content_type: Option<&str>, body: &[u8]) -> Response {
+        let format = match content_type {
+            Some(content_type) => match Format::from_content_type(content_type) {
+                Some(format) => format,
+                None => {
+                    let msg = format!("content type {} is not supported", content_type);
+                    return Response::error(415, &msg);
+                }
+            },
+            None => Format::Json,
+        };
+        let tx = match wire::decode(body, format, self.decoding) {
+            Ok(tx) => tx,
+            Err(e) => return Response::invalid(&e),
+        };
+        if let Err(e) = tx.verify() {
+            return Response::invalid(&e);
+        }
+        if let Err(response) = self.check_transaction(&tx) {
+            return response;
+        }
+        let response = Response::accepted(&tx);
+        self.accepted.insert(tx.calc_hash(), tx.clone());
+        self.chains.entry(tx.get_id().clone()).or_default().push(tx);
+        response
+    }
+
+    /// Check a transaction with a valid signature. The timestamp must be set
+    /// and not be in the future, and the transaction must not have been
+    /// accepted before. A registration must be for a new bike, and a transfer
+    /// must be signed by the current owner of a registered bike.
+    fn check_transaction(&self, tx: &Transaction) -> Result<(), Response> {
+        let timestamp = tx.get_timestamp();
+        if timestamp == 0 {
+            return Err(Response::error(400, "timestamp is zero"));
+        }
+        if timestamp > transaction::make_timestamp() + MAX_CLOCK_SKEW {
+            return Err(Response::error(400, "timestamp is in the future"));
+        }
+        if self.accepted.contains_key(&tx.calc_hash()) {
+            return Err(Response::error(409, "transaction was already accepted"));
+        }
+        let owner = self
+            .chains
+            .get(tx.get_id())
+            .and_then(|chain| chain.last())
+            .map(|last| last.get_public_key_output());
+        match (tx.get_public_key_input(), owner) {
+            (None, None) => Ok(()),
+            (None, Some(_)) => Err(Response::error(409, "bike is already registered")),
+            (Some(_), None) => Err(Response::error(409, "bike is not registered")),
+            (Some(input), Some(owner)) if input == owner => Ok(()),
+            (Some(_), Some(_)) => Err(Response::error(409, "input is not the key of the owner")),
+        }
+    }
+
+    /// Respond with the chain of the bike with a percent-encoded id
+    fn get_chain(&self, id: &str) -> Response {
+        let id = match query::decode_segment(id) {
+            Some(id) => id,
+            None => return Response::error(400, "invalid bike id"),
+        };
+        match self.chains.get(&id) {
+            Some(chain) => Response::found(query::chain_to_json(chain)),
+            None => Response::error(404, &format!("no bike {}", id)),
+        }
+    }
+
+    /// Respond with the transaction with a hash
+    fn get_transaction(&self, hash: &str) -> Response {
+        let tx = hash::str_to_hash(hash).and_then(|h| self.accepted.get(&h));
+        match tx {
+            Some(tx) => Response::found(tx.to_json()),
+            None => Response::error(404, &format!("no transaction {}", hash)),
+        }
+    }
+
+    /// Respond with the inclusion status of the transaction with a hash in
+    /// the chain of blocks that ends at the tip
+    fn get_status(&self, hash: &str) -> Response {
+        let tx_hash = match hash::str_to_hash(hash) {
+            Some(h) if self.accepted.contains_key(&h) => h,
+            _ => return Response::error(404, &format!("no transaction {}", hash)),
+        };
+        let block = self.tip.and_then(|tip| self.block_of(&tx_hash, &tip));
+        let inclusion = match block {
+            Some(block) => Inclusion::Included {
+                block,
+                proof: self.blocks[&block].block.proof(&tx_hash).unwrap(),
+            },
+            None => Inclusion::Pending,
+        };
+        Response::found(query::inclusion_to_json(&inclusion))
+    }
+
+    /// Returns a new block on top of the tip with the accepted transactions
+    /// that are not in the chain of blocks yet, like a node makes on its own,
+    /// or None if there is nothing to include. The block still has to be
+    /// mined before it is added.
+    pub fn next_block(&self) -> Option<Block> {
+        let mut transactions: Vec<Transaction> = self
+            .chains
+            .values()
+            .flat_map(|chain| chain.iter())
+            .filter(|tx| {
+                let tip = self.tip.unwrap_or(EMPTY_HASH);
+                self.block_of(&tx.calc_hash(), &tip).is_none()
+            })
+            .cloned()
+            .collect();
+        if transactions.is_empty() {
+            return None;
+        }
+        transactions.sort_by_key(|tx| tx.get_timestamp());
+        Some(Block::new(self.tip.unwrap_or(EMPTY_HASH), transactions))
+    }
+
+    /// Add an accepted block, which becomes the tip if it is higher than it
+    fn insert_block(&mut self, hash: Hash, block: Block, height: u64) {
+        let tip_height = self.tip.map(|tip| self.blocks[&tip].height).unwrap_or(0);
+        if height > tip_height {
+            self.tip = Some(hash);
+        }
+        self.blocks.insert(hash, StoredBlock { block, height });
+    }
+
+    /// Handle a posted block
+    fn post_block(&mut self, body: &[u8]) -> Response {
+        let json = String::from_utf8_lossy(body);
+        match Block::from_json(&json) {
+            Ok(block) => self.add_block(block),
+            Err(e) => Response::error(400, &e),
+        }
+    }
+
+    /// Add a block. The block must be valid, meet the difficulty and follow a
+    /// known block, and its transactions must be accepted and not already be
+    /// in a block of its branch. The longest branch is the chain of the node.
+    /// Of branches with the same length, the first one to reach that length is
+    /// kept.
+    pub fn add_block(&mut self, block: Block) -> Response {
+        if let Err(e) = block.verify() {
+            return Response::error(400, &e.to_string());
+        }
+        let hash = block.calc_hash();
+        if !mine::meets_difficulty(&hash, self.difficulty) {
+            let msg = format!("hash does not have {} leading zero bits", self.difficulty);
+            return Response::error(400, &msg);
+        }
+        if self.blocks.contains_key(&hash) {
+            return Response::error(409, "block was already accepted");
+        }
+
+        let prev_hash = block.get_prev_hash();
+        let height = match self.blocks.get(prev_hash) {
+            Some(prev) if block.verify_is_next(&prev.block) => prev.height + 1,
+            Some(_) => return Response::error(400, "timestamp is before the previous block"),
+            None if prev_hash == &EMPTY_HASH => 1,
+            None => return Response::error(409, "previous block is not known"),
+        };
+        for tx in block.get_transactions() {
+            let tx_hash = tx.calc_hash();
+            let tx_str = hash::hash_to_str(&tx_hash);
+            if !self.accepted.contains_key(&tx_hash) {
+                let msg = format!("transaction {} was not accepted", tx_str);
+                return Response::error(409, &msg);
+            }
+            if self.block_of(&tx_hash, prev_hash).is_some() {
+                let msg = format!("transaction {} is already in a block", tx_str);
+                return Response::error(409, &msg);
+            }
+        }
+
+        self.insert_block(hash, block, height);
+        Response::found(
+            json!({
+                "status": "accepted",
+                "hash": hash::hash_to_str(&hash),
+                "height": height,
+                "tip": self.tip == Some(hash),
+            })
+            .to_string(),
+        )
+    }
+
+    /// Respond with the block with a hash, or the block at the tip
+    fn get_block(&self, name: &str) -> Response {
+        let hash = match name {
+            TIP => self.tip,
+            _ => hash::str_to_hash(name),
+        };
+        match hash.and_then(|h| self.blocks.get(&h)) {
+            Some(stored) => Response::found(stored.block.to_json()),
+            None => Response::error(404, &format!("no block {}", name)),
+        }
+    }
+
+    /// Returns the hash of the block that includes a transaction, searching
+    /// back from the block with a hash to the first block of its branch
+    fn block_of(&self, tx_hash: &Hash, from: &Hash) -> Option<Hash> {
+        let mut hash = *from;
+        while let Some(stored) = self.blocks.get(&hash) {
+            let transactions = stored.block.get_transactions();
+            if transactions.iter().any(|tx| &tx.calc_hash() == tx_hash) {
+                return Some(hash);
+            }
+            hash = *stored.block.get_prev_hash();
+        }
+        None
+    }
+}
+
+// ========================================================================== //
+
+/// Run a mock node on the specified address until the process is stopped.
+/// Each connection is handled on its own thread. With a block interval, the
+/// node includes the transactions it accepted in a new block at that interval.
+pub fn serve(
+    addr: &str,
+    decoding: Decoding,
+    difficulty: u32,
+    block_interval: Option<Duration>,
+) -> Result<(), String> {
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(e) => return Err(format!("Failed to listen on {} ({})", addr, e)),
+    };
+    println!(
+        "Mock node listening on http://{}{} ({} decoding, difficulty {})",
+        addr, TRANSACTION_PATH, decoding, difficulty
+    );
+
+    let node = Arc::new(Mutex::new(MockNode::new(decoding, difficulty)));
+    if let Some(interval) = block_interval {
+        let node = node.clone();
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+            produce_block(&node, difficulty);
+        });
+    }
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("error: Failed to accept connection ({})", e);
+                continue;
+            }
+        };
+        let node = node.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &node) {
+                eprintln!("error: {}", e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Make a block of the new transactions of a node and add it. The block is
+/// mined without holding the lock, so that the node keeps handling requests.
+fn produce_block(node: &Mutex<MockNode>, difficulty: u32) {
+    let mut block = match node.lock().unwrap().next_block() {
+        Some(block) => block,
+        None => return,
+    };
+    if let Err(e) = mine::mine(&mut block, difficulty, 0, mine::attempt_limit(difficulty)) {
+        eprintln!("error: Failed to mine a block ({})", e);
+        return;
+    }
+    let hash = block.calc_hash();
+    let response = node.lock().unwrap().add_block(block);
+    match response.status {
+        200 => println!("Produced block {}", hash::hash_to_str(&hash)),
+        _ => eprintln!("error: Produced block was not added ({})", response.body),
+    }
+}
+
+/// Read a single request from a connection, handle it and write the response
+fn handle_connection(stream: TcpStream, node: &Mutex<MockNode>) -> Result<(), String> {
+    let mut reader = BufReader::new(&stream);
+    let head = read_head(&mut reader)?;
+    let response = match head.length {
+        Some(n) if n > MAX_BODY => Response::error(413, "request body is too large"),
+        _ => {
+            let mut body = vec![0; head.length.unwrap_or(0)];
+            if let Err(e) = reader.read_exact(&mut body) {
+                return Err(format!("Failed to read request body ({})", e));
+            }
+            node.lock().unwrap().handle(
+                &head.method,
+                &head.path,
+                head.content_type.as_ref().map(|s| s.as_str()),
+                &body,
+            )
+        }
+    };
+    println!(
+        "{} {} {} {}",
+        head.method, head.path, response.status, response.body
+    );
+
+    let mut stream = &stream;
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.body.len()
+    );
+    match stream
+        .write_all(head.as_bytes())
+        .and_then(|_| stream.write_all(response.body.as_bytes()))
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Failed to write response ({})", e)),
+    }
+}
+
+/// Request line and headers of a request
+struct RequestHead {
+    method: String,
+    path: String,
+    /// Value of the Content-Length header
+    length: Option<usize>,
+    /// Value of the Content-Type header
+    content_type: Option<String>,
+}
+
+/// Read the request line and headers of a request
+fn read_head<R: BufRead>(reader: &mut R) -> Result<RequestHead, String> {
+    let mut line = String::new();
+    if let Err(e) = reader.read_line(&mut line) {
+        return Err(format!("Failed to read request ({})", e));
+    }
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next()) {
+        (Some(method), Some(path)) => (String::from(method), String::from(path)),
+        _ => return Err(format!("Invalid request line '{}'", line.trim())),
+    };
+
+    let mut length = None;
+    let mut content_type = None;
+    loop {
+        let mut header = String::new();
+        match reader.read_line(&mut header) {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => return Err(format!("Failed to read request header ({})", e)),
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        let mut kv = header.splitn(2, ':');
+        if let (Some(k), Some(v)) = (kv.next(), kv.next()) {
+            let k = k.trim();
+            if k.eq_ignore_ascii_case("content-length") {
+                length = v.trim().parse::<usize>().ok();
+            } else if k.eq_ignore_ascii_case("content-type") {
+                content_type = Some(String::from(v.trim()));
+            }
+        }
+    }
+    Ok(RequestHead {
+        method,
+        path,
+        length,
+        content_type,
+    })
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::attack::ATTACKS;
+    use crate::entropy::Entropy;
+    use crate::expect::{Ledger, Verdict};
+    use crate::transaction::SigningScheme;
+
+    #[test]
+    fn test_mock_node() {
+        let mut node = MockNode::new(Decoding::Normal, 0);
+        let (t0, sk0) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let (t1, _) = Transaction::debug_make_transfer(&t0, &sk0);
+        let (t2, _) = Transaction::debug_make_transfer(&t0, &sk0);
+        let mut post = |tx: &Transaction, format: Format| {
+            let body = wire::encode(tx, format);
+            let content_type = Some(format.content_type());
+            node.handle("POST", "/transaction", content_type, &body)
+                .status
+        };
+
+        assert_eq!(post(&t1, Format::Json), 409);
+        assert_eq!(post(&t0, Format::Binary), 200);
+        assert_eq!(post(&t0, Format::Json), 409);
+        assert_eq!(post(&t1, Format::Binary), 200);
+        assert_eq!(post(&t2, Format::Json), 409);
+
+        let mut handle = |method, path, content_type, body: &str| {
+            node.handle(method, path, content_type, body.as_bytes())
+                .status
+        };
+        assert_eq!(handle("POST", "/transaction", None, "{}"), 400);
+        assert_eq!(
+            handle("POST", "/transaction", Some("text/plain"), "{}"),
+            415
+        );
+        assert_eq!(handle("GET", "/transaction", None, ""), 405);
+        assert_eq!(handle("POST", "/", None, ""), 404);
+
+        // A strict node rejects fields that a normal node ignores
+        let (t3, _) = Transaction::debug_make_register(format!("SN1337BIKF"));
+        let mut v: serde_json::Value = serde_json::from_str(&t3.to_json()).unwrap();
+        v["note"] = json!("extra");
+        let body = v.to_string();
+        let mut strict = MockNode::new(Decoding::Strict, 0);
+        let response = strict.handle("POST", "/transaction", None, body.as_bytes());
+        assert_eq!(response.status, 400);
+        assert!(response.body.contains("unknown-field"));
+        assert_eq!(handle("POST", "/transaction", None, &body), 200);
+
+        // Accepted transactions can be read back
+        let response = node.handle("GET", "/chain/SN1337BIKE", None, b"");
+        let chain = query::parse_chain(&response.body).unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[1].verify_is_next(&chain[0]), true);
+        let path = format!("/transaction/{}", hash::hash_to_str(&t1.calc_hash()));
+        let response = node.handle("GET", &path, None, b"");
+        assert_eq!(response.body, t1.to_json());
+        assert_eq!(node.handle("GET", "/chain/SN0", None, b"").status, 404);
+    }
+
+    #[test]
+    fn test_mock_rules() {
+        // The node rejects every attack by its own rules, as the client expects
+        let mut entropy = Entropy::new(Some(7), None);
+        for (i, attack) in ATTACKS.iter().enumerate() {
+            let mut node = MockNode::new(Decoding::Normal, 0);
+            let mut ledger = Ledger::new();
+            let id = format!("SN{}BIKE", i);
+            for job in attack.make_jobs(id, SigningScheme::default(), &mut entropy) {
+                let expected = ledger.judge(&job);
+                let response = node.handle("POST", "/transaction", None, job.body().as_bytes());
+                assert_eq!(
+                    response.status == 200,
+                    expected == Verdict::Accept,
+                    "{} of {}: {}",
+                    job.label,
+                    attack,
+                    response.body
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_mock_blocks() {
+        let mut node = MockNode::new(Decoding::Normal, 4);
+        let (t0, _) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let (t1, _) = Transaction::debug_make_register(format!("SN4242BIKE"));
+        for tx in &[&t0, &t1] {
+            let response = node.handle("POST", "/transaction", None, tx.to_json().as_bytes());
+            assert_eq!(response.status, 200);
+        }
+        let mut post = |prev_hash, txs: Vec<Transaction>, start| {
+            let mut block = Block::new(prev_hash, txs);
+            mine::mine(&mut block, 4, start, mine::attempt_limit(4)).unwrap();
+            let response = node.handle("POST", "/block", None, block.to_json().as_bytes());
+            (block.calc_hash(), response.status)
+        };
+
+        let (b0, status) = post(EMPTY_HASH, vec![t0.clone()], 0);
+        assert_eq!(status, 200);
+        assert_eq!(post(b0, vec![t0.clone()], 0).1, 409);
+        assert_eq!(post(b0, vec![t1.clone()], 0).1, 200);
+        assert_eq!(post(EMPTY_HASH, vec![t1.clone()], 0).1, 200);
+        assert_eq!(post([1; 32], vec![t1.clone()], 0).1, 409);
+
+        // Another fork of the same length does not take over the tip
+        let (fork, _) = post(EMPTY_HASH, vec![t1.clone()], 1 << 32);
+        let tip = node.handle("GET", "/block/tip", None, b"").body;
+        let tip = Block::from_json(&tip).unwrap();
+        assert_eq!(tip.get_prev_hash(), &b0);
+        let path = format!("/block/{}", hash::hash_to_str(&fork));
+        assert_eq!(node.handle("GET", &path, None, b"").status, 200);
+
+        let mut block = Block::new(EMPTY_HASH, vec![t0]);
+        block.set_nonce(0);
+        while mine::meets_difficulty(&block.calc_hash(), 4) {
+            block.set_nonce(block.get_nonce() + 1);
+        }
+        let response = node.handle("POST", "/block", None, block.to_json().as_bytes());
+        assert_eq!(response.status, 400);
+
+        // The node includes new transactions in a block of its own
+        let (t2, _) = Transaction::debug_make_register(format!("SN9999BIKE"));
+        node.handle("POST", "/transaction", None, t2.to_json().as_bytes());
+        let path = format!("/transaction/{}/status", hash::hash_to_str(&t2.calc_hash()));
+        let status = |node: &mut MockNode| {
+            let response = node.handle("GET", &path, None, b"");
+            query::parse_inclusion(&response.body).unwrap()
+        };
+        assert_eq!(status(&mut node), Inclusion::Pending);
+        let mut block = node.next_block().unwrap();
+        mine::mine(&mut block, 4, 0, mine::attempt_limit(4)).unwrap();
+        let produced = block.calc_hash();
+        assert_eq!(node.add_block(block).status, 200);
+        assert!(node.next_block().is_none());
+        match status(&mut node) {
+            Inclusion::Included { block, proof } => {
+                assert_eq!(block, produced);
+                let stored = &node.blocks[&block].block;
+                assert!(stored.verify_proof(&t2.calc_hash(), &proof));
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+}
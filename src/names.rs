@@ -0,0 +1,30 @@
+use rand::prelude::*;
+
+// ========================================================================== //
+
+/// Read the list of names that is bundled with the client
+pub fn load() -> Vec<String> {
+    std::include_str!("../names.txt")
+        .split("\n")
+        .map(|n| String::from(n.trim()))
+        .filter(|n| !n.is_empty())
+        .collect()
+}
+
+// ========================================================================== //
+
+/// Number of different numbers that a generated name ends with
+pub const NAME_NUMBERS: usize = 1000;
+
+/// Returns the number of different names that can be generated from the list
+/// of names
+pub fn capacity(names: &[String]) -> usize {
+    names.len() * NAME_NUMBERS
+}
+
+/// Generate a random name from the list of names
+pub fn gen_rand_name<R: Rng>(names: &[String], rng: &mut R) -> String {
+    let name_idx = rng.gen_range(0, names.len());
+    let rand_idx = rng.gen_range(0, NAME_NUMBERS);
+    format!("{}_{}", names[name_idx], rand_idx)
+}
@@ -0,0 +1,134 @@
+use std::time::Duration;
+
+// ========================================================================== //
+
+/// Profile of the target send rate over the course of an open-loop run. The
+/// rate increases linearly from zero to 'rate' during the ramp-up, stays at
+/// 'rate' during the hold and decreases linearly to zero during the ramp-down.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateProfile {
+    /// Target rate during the hold phase (transactions per second)
+    pub rate: f64,
+    /// Duration of the ramp-up phase
+    pub ramp_up: Duration,
+    /// Duration of the hold phase
+    pub hold: Duration,
+    /// Duration of the ramp-down phase
+    pub ramp_down: Duration,
+}
+
+impl RateProfile {
+    /// Create a profile with a constant rate for the specified duration
+    pub fn constant(rate: f64, duration: Duration) -> RateProfile {
+        RateProfile {
+            rate,
+            ramp_up: Duration::from_secs(0),
+            hold: duration,
+            ramp_down: Duration::from_secs(0),
+        }
+    }
+
+    /// Returns the total duration of the profile
+    pub fn duration(&self) -> Duration {
+        self.ramp_up + self.hold + self.ramp_down
+    }
+
+    /// Returns the target rate at time 't' since the start of the run
+    pub fn rate_at(&self, t: Duration) -> f64 {
+        let t = t.as_secs_f64();
+        let (up, hold, down) = self.phases();
+        if t < up {
+            self.rate * t / up
+        } else if t < up + hold {
+            self.rate
+        } else if t < up + hold + down {
+            self.rate * (1.0 - (t - up - hold) / down)
+        } else {
+            0.0
+        }
+    }
+
+    /// Returns the total number of transactions sent over the whole profile
+    pub fn total_count(&self) -> u64 {
+        let (up, hold, down) = self.phases();
+        (self.rate * (up / 2.0 + hold + down / 2.0)).floor() as u64
+    }
+
+    /// Returns the time, since the start of the run, at which the n:th
+    /// (zero-based) transaction should be sent. Returns None if the profile
+    /// ends before that.
+    pub fn send_time(&self, n: u64) -> Option<Duration> {
+        if self.rate <= 0.0 || n >= self.total_count() {
+            return None;
+        }
+        let n = n as f64;
+        let r = self.rate;
+        let (up, hold, down) = self.phases();
+
+        // Number of transactions sent at the end of each phase
+        let n_up = r * up / 2.0;
+        let n_hold = n_up + r * hold;
+
+        let t = if n < n_up {
+            (2.0 * up * n / r).sqrt()
+        } else if n < n_hold {
+            up + (n - n_up) / r
+        } else {
+            let x = 1.0 - 2.0 * (n - n_hold) / (r * down);
+            up + hold + down * (1.0 - x.max(0.0).sqrt())
+        };
+        Some(Duration::from_secs_f64(t))
+    }
+
+    /// Returns the durations of the phases in seconds
+    fn phases(&self) -> (f64, f64, f64) {
+        (
+            self.ramp_up.as_secs_f64(),
+            self.hold.as_secs_f64(),
+            self.ramp_down.as_secs_f64(),
+        )
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_schedule() {
+        let p = RateProfile::constant(10.0, Duration::from_secs(2));
+        assert_eq!(p.total_count(), 20);
+        assert_eq!(p.send_time(0), Some(Duration::from_secs(0)));
+        assert_eq!(p.send_time(5), Some(Duration::from_millis(500)));
+        assert_eq!(p.send_time(19), Some(Duration::from_millis(1900)));
+        assert_eq!(p.send_time(20), None);
+    }
+
+    #[test]
+    fn test_ramp_schedule() {
+        let p = RateProfile {
+            rate: 100.0,
+            ramp_up: Duration::from_secs(2),
+            hold: Duration::from_secs(1),
+            ramp_down: Duration::from_secs(2),
+        };
+        assert_eq!(p.total_count(), 300);
+        assert_eq!(p.rate_at(Duration::from_secs(1)), 50.0);
+        assert_eq!(p.rate_at(Duration::from_millis(2500)), 100.0);
+        assert_eq!(p.rate_at(Duration::from_secs(4)), 50.0);
+
+        // Send times are increasing and stay within the profile
+        let mut prev = Duration::from_secs(0);
+        for n in 0..p.total_count() {
+            let t = p.send_time(n).unwrap();
+            assert!(t >= prev);
+            assert!(t <= p.duration());
+            prev = t;
+        }
+
+        // Half of the ramp-up transactions have been sent after sqrt(2) seconds
+        let t = p.send_time(50).unwrap().as_secs_f64();
+        assert!((t - 2.0_f64.sqrt()).abs() < 1e-9);
+    }
+}
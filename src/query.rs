@@ -0,0 +1,372 @@
+use crate::block::Block;
+use crate::chain::{self, LinkReport};
+use crate::hash::{self, Hash, Hashable};
+use crate::merkle::MerkleProof;
+use crate::rest::RestClient;
+use crate::transaction::Transaction;
+use serde_json::{json, Value};
+use std::collections::HashSet;
+
+// ========================================================================== //
+
+/// Path of the chain endpoint, relative to the root of a node. The chain of a
+/// bike is at '/chain/<id>'.
+pub const CHAIN_PATH: &str = "/chain";
+
+/// Path of the block endpoint, relative to the root of a node. Blocks are
+/// posted to it, and the block with a hash is at '/block/<hash>'.
+pub const BLOCK_PATH: &str = "/block";
+
+/// Name of the block at the tip of the longest chain of blocks of a node
+pub const TIP: &str = "tip";
+
+/// Last path segment of the inclusion status of a transaction, which is at
+/// '<transaction endpoint>/<hash>/status'
+pub const STATUS: &str = "status";
+
+// ========================================================================== //
+
+/// Returns the root URL of a node, which is its transaction endpoint without
+/// the last path segment
+fn node_root(endpoint: &str) -> &str {
+    let endpoint = endpoint.trim_end_matches('/');
+    let path_start = endpoint.find("://").map(|i| i + 3).unwrap_or(0);
+    match endpoint[path_start..].rfind('/') {
+        Some(i) => &endpoint[..path_start + i],
+        None => endpoint,
+    }
+}
+
+/// Returns the URL of the chain of a bike on the node of a transaction
+/// endpoint
+pub fn chain_url(endpoint: &str, id: &str) -> String {
+    format!(
+        "{}{}/{}",
+        node_root(endpoint),
+        CHAIN_PATH,
+        encode_segment(id)
+    )
+}
+
+/// Returns the URL of the block endpoint on the node of a transaction endpoint
+pub fn block_url(endpoint: &str) -> String {
+    format!("{}{}", node_root(endpoint), BLOCK_PATH)
+}
+
+/// Returns the URL of a single transaction, which is below the transaction
+/// endpoint
+pub fn transaction_url(endpoint: &str, hash: &Hash) -> String {
+    format!(
+        "{}/{}",
+        endpoint.trim_end_matches('/'),
+        hash::hash_to_str(hash)
+    )
+}
+
+/// Returns the URL of the inclusion status of a transaction
+pub fn status_url(endpoint: &str, hash: &Hash) -> String {
+    format!("{}/{}", transaction_url(endpoint, hash), STATUS)
+}
+
+/// Percent-encode a path segment
+pub fn encode_segment(s: &str) -> String {
+    s.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// Decode a percent-encoded path segment. Returns None if the encoding or
+/// the decoded text is invalid.
+pub fn decode_segment(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = s.get(i + 1..i + 3)?;
+            decoded.push(hash::hex_byte(byte)?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+// ========================================================================== //
+
+/// Fetch the chain of a bike from a node, oldest transaction first. The chain
+/// of a bike that the node does not know is empty.
+pub fn fetch_chain(
+    client: &RestClient,
+    endpoint: &str,
+    id: &str,
+) -> Result<Vec<Transaction>, String> {
+    let url = chain_url(endpoint, id);
+    match client.get(&url) {
+        Ok((_, 404)) => Ok(Vec::new()),
+        Ok((body, status)) if (200..300).contains(&status) => parse_chain(&body),
+        Ok((body, status)) => Err(format!(
+            "Node responded with code {} ({})",
+            status,
+            body.trim()
+        )),
+        Err(e) => Err(format!("Failed to get {} ({})", url, e)),
+    }
+}
+
+/// Fetch a single transaction from a node. Returns None if the node does not
+/// have the transaction.
+pub fn fetch_transaction(
+    client: &RestClient,
+    endpoint: &str,
+    hash: &Hash,
+) -> Result<Option<Transaction>, String> {
+    let url = transaction_url(endpoint, hash);
+    match client.get(&url) {
+        Ok((_, 404)) => Ok(None),
+        Ok((body, status)) if (200..300).contains(&status) => match Transaction::from_json(&body) {
+            Ok(tx) => Ok(Some(tx)),
+            Err(e) => Err(format!("Invalid transaction ({})", e)),
+        },
+        Ok((body, status)) => Err(format!(
+            "Node responded with code {} ({})",
+            status,
+            body.trim()
+        )),
+        Err(e) => Err(format!("Failed to get {} ({})", url, e)),
+    }
+}
+
+/// Fetch the block at the tip of the longest chain of blocks of a node.
+/// Returns None if the node has no blocks.
+pub fn fetch_tip(client: &RestClient, endpoint: &str) -> Result<Option<Block>, String> {
+    fetch_block_named(client, endpoint, TIP)
+}
+
+/// Fetch the block with a hash from a node. Returns None if the node does not
+/// have the block.
+pub fn fetch_block(
+    client: &RestClient,
+    endpoint: &str,
+    hash: &Hash,
+) -> Result<Option<Block>, String> {
+    fetch_block_named(client, endpoint, &hash::hash_to_str(hash))
+}
+
+/// Fetch the block with a name, which is a hash or 'tip', from a node
+fn fetch_block_named(
+    client: &RestClient,
+    endpoint: &str,
+    name: &str,
+) -> Result<Option<Block>, String> {
+    let url = format!("{}/{}", block_url(endpoint), name);
+    match client.get(&url) {
+        Ok((_, 404)) => Ok(None),
+        Ok((body, status)) if (200..300).contains(&status) => Block::from_json(&body).map(Some),
+        Ok((body, status)) => Err(format!(
+            "Node responded with code {} ({})",
+            status,
+            body.trim()
+        )),
+        Err(e) => Err(format!("Failed to get {} ({})", url, e)),
+    }
+}
+
+/// Whether a node has included a transaction in a block of its chain
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inclusion {
+    /// The node does not know the transaction
+    Unknown,
+    /// The node accepted the transaction, but no block of its chain has it
+    Pending,
+    /// The transaction is in a block of the chain of the node
+    Included {
+        /// Hash of the block
+        block: Hash,
+        /// Proof that the transaction is included in the block
+        proof: MerkleProof,
+    },
+}
+
+/// Fetch the inclusion status of a transaction from a node
+pub fn fetch_inclusion(
+    client: &RestClient,
+    endpoint: &str,
+    hash: &Hash,
+) -> Result<Inclusion, String> {
+    let url = status_url(endpoint, hash);
+    match client.get(&url) {
+        Ok((_, 404)) => Ok(Inclusion::Unknown),
+        Ok((body, status)) if (200..300).contains(&status) => parse_inclusion(&body),
+        Ok((body, status)) => Err(format!(
+            "Node responded with code {} ({})",
+            status,
+            body.trim()
+        )),
+        Err(e) => Err(format!("Failed to get {} ({})", url, e)),
+    }
+}
+
+/// Parse the inclusion status of a transaction, which is a JSON object with
+/// the status 'pending' or 'included'. An included transaction has the hash
+/// of its block and the proof of its inclusion.
+pub fn parse_inclusion(json: &str) -> Result<Inclusion, String> {
+    let v: Value = match serde_json::from_str(json) {
+        Ok(v) => v,
+        Err(e) => return Err(format!("Invalid inclusion status ({})", e)),
+    };
+    match v["status"].as_str() {
+        Some("pending") => Ok(Inclusion::Pending),
+        Some("included") => {
+            let block = v["block"].as_str().and_then(hash::str_to_hash);
+            let block = match block {
+                Some(block) => block,
+                None => return Err(format!("Invalid inclusion status (block is not a hash)")),
+            };
+            let proof = MerkleProof::from_json(&v["proof"].to_string())?;
+            Ok(Inclusion::Included { block, proof })
+        }
+        _ => Err(format!("Invalid inclusion status ({})", json.trim())),
+    }
+}
+
+/// Returns the inclusion status of a transaction as JSON
+pub fn inclusion_to_json(inclusion: &Inclusion) -> String {
+    match inclusion {
+        Inclusion::Unknown => json!({ "status": "unknown" }),
+        Inclusion::Pending => json!({ "status": "pending" }),
+        Inclusion::Included { block, proof } => {
+            let proof: Value = serde_json::from_str(&proof.to_json()).unwrap();
+            json!({
+                "status": "included",
+                "block": hash::hash_to_str(block),
+                "proof": proof,
+            })
+        }
+    }
+    .to_string()
+}
+
+/// Parse a chain, which is a JSON array of transactions
+pub fn parse_chain(json: &str) -> Result<Vec<Transaction>, String> {
+    let values: Vec<Value> = match serde_json::from_str(json) {
+        Ok(values) => values,
+        Err(e) => return Err(format!("Invalid chain ({})", e)),
+    };
+    let mut chain = Vec::with_capacity(values.len());
+    for (i, v) in values.iter().enumerate() {
+        match Transaction::from_json(&v.to_string()) {
+            Ok(tx) => chain.push(tx),
+            Err(e) => return Err(format!("Invalid transaction {} of chain ({})", i, e)),
+        }
+    }
+    Ok(chain)
+}
+
+/// Returns a chain as a JSON array of transactions
+pub fn chain_to_json(chain: &[Transaction]) -> String {
+    let values: Vec<Value> = chain
+        .iter()
+        .map(|tx| serde_json::from_str(&tx.to_json()).unwrap())
+        .collect();
+    Value::Array(values).to_string()
+}
+
+// ========================================================================== //
+
+/// Transaction of a chain that was fetched from a node, with the results of
+/// the local checks
+#[derive(Clone, Debug)]
+pub struct Link {
+    /// Transaction of the node
+    pub tx: Transaction,
+    /// Result of the verification of the transaction as a link of the chain
+    pub report: LinkReport,
+    /// Whether the client submitted the transaction and a node accepted it
+    pub submitted: bool,
+}
+
+/// Verify the chain of a node, and mark the transactions that are among the
+/// transactions that the client submitted
+pub fn check_chain(chain: Vec<Transaction>, submitted: &[Transaction]) -> Vec<Link> {
+    let submitted: HashSet<Hash> = submitted.iter().map(|tx| tx.calc_hash()).collect();
+    let report = chain::verify_chain(&chain);
+    chain
+        .into_iter()
+        .zip(report.links)
+        .map(|(tx, report)| Link {
+            submitted: submitted.contains(&report.hash),
+            tx,
+            report,
+        })
+        .collect()
+}
+
+/// Returns the index of the first transaction where the chain of a node
+/// differs from the transactions that the client submitted for the bike, or
+/// None if they are the same
+pub fn divergence(chain: &[Transaction], submitted: &[Transaction]) -> Option<usize> {
+    let len = chain.len().max(submitted.len());
+    (0..len).find(|&i| match (chain.get(i), submitted.get(i)) {
+        (Some(a), Some(b)) => a.calc_hash() != b.calc_hash(),
+        _ => true,
+    })
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_urls() {
+        let endpoint = "http://localhost:8000/transaction";
+        assert_eq!(
+            chain_url(endpoint, "Åsa_7/b"),
+            "http://localhost:8000/chain/%C3%85sa_7%2Fb"
+        );
+        assert_eq!(decode_segment("%C3%85sa_7%2Fb").unwrap(), "Åsa_7/b");
+        assert!(decode_segment("%zz").is_none());
+        assert!(decode_segment("%+f").is_none());
+        assert_eq!(chain_url("http://node", "a"), "http://node/chain/a");
+        assert_eq!(block_url(endpoint), "http://localhost:8000/block");
+        assert_eq!(
+            status_url(endpoint, &[0; 32]),
+            format!("{}/{}/status", endpoint, "00".repeat(32))
+        );
+
+        let hash = [0xab; 32];
+        let url = transaction_url("http://node/api/transaction/", &hash);
+        assert_eq!(
+            url,
+            format!("http://node/api/transaction/{}", "ab".repeat(32))
+        );
+    }
+
+    #[test]
+    fn test_check_chain() {
+        let (t0, sk0) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let (t1, sk1) = Transaction::debug_make_transfer(&t0, &sk0);
+        let (t2, _) = Transaction::debug_make_transfer(&t1, &sk1);
+        let (other, _) = Transaction::debug_make_transfer(&t0, &sk0);
+
+        // The node skipped the second transfer and has another one instead
+        let chain = vec![t0.clone(), t1.clone(), other];
+        let submitted = vec![t0.clone(), t1.clone(), t2];
+        let parsed = parse_chain(&chain_to_json(&chain)).unwrap();
+        let links = check_chain(parsed, &submitted);
+        let valid: Vec<bool> = links.iter().map(|l| l.report.is_valid()).collect();
+        let mine: Vec<bool> = links.iter().map(|l| l.submitted).collect();
+        assert_eq!(valid, vec![true, true, false]);
+        assert_eq!(mine, vec![true, true, false]);
+        assert_eq!(divergence(&chain, &submitted), Some(2));
+        assert_eq!(divergence(&chain[..2], &submitted[..2]), None);
+    }
+}
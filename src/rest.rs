@@ -1,18 +1,387 @@
-use reqwest;
-
-pub fn post(url: &str, body: &str) -> Result<(String, u16), String> {
-    let client = reqwest::Client::new();
-    match client
-        .post(url)
-        .body(String::from(body))
-        .header("Content-type", "application/json")
-        .send()
+use reqwest::{self, Url};
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::net::IpAddr;
+use std::thread;
+use std::time::Duration;
+
+// ========================================================================== //
+
+/// Default time to wait for a connection to a node
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Default time to wait for the response to a request
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default number of times a request is retried after a transient failure
+pub const DEFAULT_RETRIES: u32 = 2;
+
+/// Time to wait before the first retry. The time is doubled for every retry
+/// after it.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+
+// ========================================================================== //
+
+/// Failure of a request that did not produce a usable response
+#[derive(Clone, Debug, PartialEq)]
+pub enum RestError {
+    /// The host name could not be resolved
+    Dns(String),
+    /// The host refused the connection
+    Refused(String),
+    /// No connection could be made to the host for another reason, or the
+    /// connection failed after the request may have been sent
+    Connect(String),
+    /// The connection or the response timed out
+    Timeout(String),
+    /// The TLS handshake failed
+    Tls(String),
+    /// The node is unavailable and responded with the status code and body
+    Status(u16, String),
+    /// The response body could not be read
+    Body(String),
+    /// Any other failure, like an invalid URL
+    Other(String),
+}
+
+impl RestError {
+    /// Returns the name of the kind of failure
+    pub fn kind(&self) -> &'static str {
+        match self {
+            RestError::Dns(_) => "dns",
+            RestError::Refused(_) => "refused",
+            RestError::Connect(_) => "connect",
+            RestError::Timeout(_) => "timeout",
+            RestError::Tls(_) => "tls",
+            RestError::Status(_, _) => "status",
+            RestError::Body(_) => "body",
+            RestError::Other(_) => "other",
+        }
+    }
+
+    /// Returns whether the request can be retried. Only failures where the
+    /// request was never written, since the host name could not be resolved
+    /// or the host refused the connection, are retried, since sending a
+    /// transaction twice would make the node reject it as a replay. A node
+    /// that responds that it is unavailable did not process the request
+    /// either.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            RestError::Dns(_) | RestError::Refused(_) => true,
+            RestError::Status(status, _) => is_unavailable(*status),
+            _ => false,
+        }
+    }
+
+    /// Classify an error of the HTTP client. The kind of error is taken from
+    /// the errors themselves and never from the message, which contains the
+    /// URL.
+    fn from_reqwest(e: &reqwest::Error) -> RestError {
+        let mut msg = e.to_string();
+        let mut source = e.source();
+        while let Some(s) = source {
+            let cause = s.to_string();
+            if !msg.contains(&cause) {
+                msg.push_str(&format!(": {}", cause));
+            }
+            source = s.source();
+        }
+        if e.is_timeout() {
+            return RestError::Timeout(msg);
+        }
+        if let Some(status) = e.status() {
+            return RestError::Status(status.as_u16(), msg);
+        }
+        let hyper_error = e
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<hyper::Error>());
+        match hyper_error {
+            Some(inner) if inner.is_connect() => {
+                RestError::from_connect(inner.source(), e.url(), msg)
+            }
+            _ => RestError::Other(msg),
+        }
+    }
+
+    /// Classify the error of a connection that could not be made, from the
+    /// cause of the error and the URL of the request
+    fn from_connect(
+        cause: Option<&(dyn Error + 'static)>,
+        url: Option<&Url>,
+        msg: String,
+    ) -> RestError {
+        let io_error = match cause.and_then(|c| c.downcast_ref::<io::Error>()) {
+            Some(io_error) => io_error,
+            None => return RestError::Connect(msg),
+        };
+        let is_tls = io_error
+            .get_ref()
+            .map(|inner| inner.is::<native_tls::Error>())
+            .unwrap_or(false);
+        if is_tls {
+            return RestError::Tls(msg);
+        }
+        match io_error.kind() {
+            io::ErrorKind::TimedOut => RestError::Timeout(msg),
+            io::ErrorKind::ConnectionRefused => RestError::Refused(msg),
+            io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::AddrInUse
+            | io::ErrorKind::AddrNotAvailable
+            | io::ErrorKind::PermissionDenied
+            | io::ErrorKind::InvalidInput => RestError::Connect(msg),
+            // Any other failure before a connection is made is from the
+            // lookup, which only happens for host names
+            _ if needs_lookup(url) => RestError::Dns(msg),
+            _ => RestError::Connect(msg),
+        }
+    }
+}
+
+impl Display for RestError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RestError::Status(status, body) => write!(f, "status {} ({})", status, body),
+            RestError::Dns(msg)
+            | RestError::Refused(msg)
+            | RestError::Connect(msg)
+            | RestError::Timeout(msg)
+            | RestError::Tls(msg)
+            | RestError::Body(msg)
+            | RestError::Other(msg) => write!(f, "{} error ({})", self.kind(), msg),
+        }
+    }
+}
+
+/// Returns whether a status code means that the node did not process the
+/// request since it is unavailable
+fn is_unavailable(status: u16) -> bool {
+    match status {
+        502 | 503 | 504 => true,
+        _ => false,
+    }
+}
+
+/// Returns whether the host of a URL is a name that must be looked up, rather
+/// than an IP address
+fn needs_lookup(url: Option<&Url>) -> bool {
+    let host = match url.and_then(|url| url.host_str()) {
+        Some(host) => host.trim_start_matches('[').trim_end_matches(']'),
+        None => return false,
+    };
+    host.parse::<IpAddr>().is_err()
+}
+
+// ========================================================================== //
+
+/// Configuration of a REST client
+#[derive(Clone, Debug, PartialEq)]
+pub struct RestConfig {
+    /// Time to wait for a connection
+    pub connect_timeout: Duration,
+    /// Time to wait for the response to a request
+    pub timeout: Duration,
+    /// Number of times a request is retried after a transient failure
+    pub retries: u32,
+}
+
+impl Default for RestConfig {
+    fn default() -> RestConfig {
+        RestConfig {
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            timeout: DEFAULT_TIMEOUT,
+            retries: DEFAULT_RETRIES,
+        }
+    }
+}
+
+// ========================================================================== //
+
+/// Client for the REST API of the nodes. The client keeps a pool of
+/// connections and can be cloned cheaply to share the pool between threads.
+#[derive(Clone, Debug)]
+pub struct RestClient {
+    /// HTTP client with the connection pool
+    client: reqwest::Client,
+    /// Number of times a request is retried after a transient failure
+    retries: u32,
+}
+
+impl RestClient {
+    pub fn new(config: &RestConfig) -> Result<RestClient, RestError> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.timeout)
+            .build();
+        match client {
+            Ok(client) => Ok(RestClient {
+                client,
+                retries: config.retries,
+            }),
+            Err(e) => Err(RestError::from_reqwest(&e)),
+        }
+    }
+
+    /// Post a body with a content type and return the response body and
+    /// status code. Any response is returned, except when the node is
+    /// unavailable. Transient failures are retried with exponential backoff.
+    pub fn post(
+        &self,
+        url: &str,
+        body: &[u8],
+        content_type: &str,
+    ) -> Result<(String, u16), RestError> {
+        self.with_retries(|| {
+            let response = self
+                .client
+                .post(url)
+                .body(body.to_vec())
+                .header("Content-type", content_type)
+                .send();
+            read_response(response)
+        })
+    }
+
+    /// Get a resource and return the response body and status code. Failures
+    /// are handled like for 'post'.
+    pub fn get(&self, url: &str) -> Result<(String, u16), RestError> {
+        self.with_retries(|| read_response(self.client.get(url).send()))
+    }
+
+    /// Make a request, and make it again after a transient failure until the
+    /// retries run out. The wait before each retry doubles, starting from
+    /// INITIAL_BACKOFF.
+    fn with_retries<F>(&self, request: F) -> Result<(String, u16), RestError>
+    where
+        F: Fn() -> Result<(String, u16), RestError>,
     {
-        Ok(mut o) => {
-            let msg = format!("{}", o.text().unwrap());
-            let status = o.status().as_u16();
-            Ok((msg, status))
+        let mut backoff = INITIAL_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            let result = request();
+            match result {
+                Err(ref e) if e.is_transient() && attempt < self.retries => {}
+                _ => return result,
+            }
+            thread::sleep(backoff);
+            backoff *= 2;
+            attempt += 1;
         }
-        Err(e) => Err(format!("{}", e)),
+    }
+}
+
+/// Returns the body and status code of a response, or the error if the node
+/// did not respond or is unavailable
+fn read_response(
+    response: Result<reqwest::Response, reqwest::Error>,
+) -> Result<(String, u16), RestError> {
+    let mut response = match response {
+        Ok(response) => response,
+        Err(e) => return Err(RestError::from_reqwest(&e)),
+    };
+    let status = response.status().as_u16();
+    let text = match response.text() {
+        Ok(text) => text,
+        Err(e) => return Err(RestError::Body(e.to_string())),
+    };
+    match is_unavailable(status) {
+        true => Err(RestError::Status(status, text)),
+        false => Ok((text, status)),
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_connect_error() {
+        // Find a port that nothing listens on
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let client = RestClient::new(&RestConfig {
+            retries: 1,
+            ..RestConfig::default()
+        })
+        .unwrap();
+        let err = client
+            .post(
+                &format!("http://{}/transaction", addr),
+                b"{}",
+                "application/json",
+            )
+            .unwrap_err();
+        assert_eq!(err.kind(), "refused");
+        assert!(err.is_transient());
+        assert!(!RestError::Timeout(String::new()).is_transient());
+        assert!(!RestError::Connect(String::new()).is_transient());
+    }
+
+    #[test]
+    fn test_retries() {
+        // A node that is unavailable for the first request of each pair
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = std::thread::spawn(move || {
+            for (i, stream) in listener.incoming().take(4).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 4096];
+                let _ = std::io::Read::read(&mut stream, &mut buf);
+                let status = match i % 2 {
+                    0 => "503 Service Unavailable",
+                    _ => "200 OK",
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{{}}",
+                    status
+                );
+                std::io::Write::write_all(&mut stream, response.as_bytes()).unwrap();
+            }
+        });
+        let client = RestClient::new(&RestConfig {
+            retries: 1,
+            ..RestConfig::default()
+        })
+        .unwrap();
+        let url = format!("http://{}/transaction", addr);
+        let posted = client.post(&url, b"{}", "application/json");
+        assert_eq!(posted.unwrap(), (String::from("{}"), 200));
+        assert_eq!(client.get(&url).unwrap(), (String::from("{}"), 200));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_closed_connection() {
+        // A node that accepts the connection and closes it without a
+        // response, on a host whose name looks like a connection error
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = std::thread::spawn(move || {
+            let mut count = 0;
+            for stream in listener.incoming().take(1) {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 4096];
+                let _ = std::io::Read::read(&mut stream, &mut buf);
+                count += 1;
+            }
+            count
+        });
+        let client = RestClient::new(&RestConfig::default()).unwrap();
+        let err = client
+            .post(
+                &format!("http://{}/connect/dns/tls/transaction", addr),
+                b"{}",
+                "application/json",
+            )
+            .unwrap_err();
+        assert!(!err.is_transient(), "{}", err);
+        assert_eq!(accepted.join().unwrap(), 1);
+        assert!(needs_lookup(Url::parse("http://node.local/").ok().as_ref()));
+        assert!(!needs_lookup(Url::parse("http://[::1]:80/").ok().as_ref()));
     }
 }
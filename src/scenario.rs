@@ -0,0 +1,335 @@
+use crate::cli;
+use crate::entropy::Entropy;
+use crate::load::{self, LoadConfig};
+use crate::profile::RateProfile;
+use crate::rest::{RestClient, RestConfig};
+use crate::stats::RunStats;
+use crate::targets::{Strategy, Targets};
+use crate::transaction::{Decoding, SigningScheme, Timestamp};
+use crate::wire::Format;
+use crate::workload::{Mix, MixedWorkload, Workload};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+
+// ========================================================================== //
+
+/// Declarative description of a load run. Scenarios are stored as JSON, for
+/// example:
+///
+/// {
+///   "name": "transfers",
+///   "targets": ["http://node1:8000/transaction", "http://node2:8000/transaction"],
+///   "strategy": "sticky",
+///   "workers": 8,
+///   "rate": 50,
+///   "rampUp": 5,
+///   "duration": 30,
+///   "mix": { "register": 1, "transfer": 3, "invalid": 0.1 },
+///   "seed": 42,
+///   "clock": 1600000000,
+///   "assertions": { "maxErrors": 0, "maxP99Ms": 250 }
+/// }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Scenario {
+    /// Name of the scenario
+    #[serde(default)]
+    pub name: String,
+    /// Transaction endpoints of the nodes
+    #[serde(default = "default_targets")]
+    pub targets: Vec<String>,
+    /// Strategy for distributing the transactions over the targets
+    #[serde(default)]
+    pub strategy: Strategy,
+    /// Weight of each target for the weighted strategy
+    #[serde(default)]
+    pub weights: Option<Vec<f64>>,
+    /// Wire format of each target, or a single format for all targets
+    #[serde(default)]
+    pub formats: Option<Vec<Format>>,
+    /// Number of concurrent workers
+    #[serde(default = "default_workers")]
+    pub workers: usize,
+    /// Number of transactions to send
+    #[serde(default)]
+    pub count: Option<u32>,
+    /// Duration of the run in seconds. With a rate, this is the duration the
+    /// rate is held.
+    #[serde(default)]
+    pub duration: Option<f64>,
+    /// Target rate in transactions per second
+    #[serde(default)]
+    pub rate: Option<f64>,
+    /// Seconds to ramp up to the target rate
+    #[serde(default)]
+    pub ramp_up: f64,
+    /// Seconds to ramp down from the target rate
+    #[serde(default)]
+    pub ramp_down: f64,
+    /// Mix of transactions
+    #[serde(default)]
+    pub mix: Mix,
+    /// Scheme of the bytes that are signed for each transaction
+    #[serde(default)]
+    pub signing: SigningScheme,
+    /// Decoding mode of the parser of the nodes
+    #[serde(default)]
+    pub decoding: Decoding,
+    /// Seconds to wait for a connection to a node
+    #[serde(default)]
+    pub connect_timeout: Option<f64>,
+    /// Seconds to wait for the response to a request
+    #[serde(default)]
+    pub timeout: Option<f64>,
+    /// Number of retries when a node cannot be reached or is unavailable
+    #[serde(default)]
+    pub retries: Option<u32>,
+    /// Seed for the names, keys and random choices of the run
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Unix time that the virtual clock of the run starts at. Without a
+    /// clock, timestamps are taken from the system clock.
+    #[serde(default)]
+    pub clock: Option<Timestamp>,
+    /// Assertions on the statistics of the run
+    #[serde(default)]
+    pub assertions: Assertions,
+}
+
+/// Assertions on the statistics of a run. Assertions that are not specified
+/// are not checked.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Assertions {
+    /// Smallest number of accepted transactions
+    pub min_accepted: Option<u64>,
+    /// Largest number of rejected transactions
+    pub max_rejected: Option<u64>,
+    /// Largest number of requests that failed without a response
+    pub max_errors: Option<u64>,
+    /// Largest number of responses that differed from the expected verdict.
+    /// When this is not specified, no such responses are allowed.
+    pub max_mismatches: Option<u64>,
+    /// Smallest throughput in transactions per second
+    pub min_throughput: Option<f64>,
+    /// Largest median latency in milliseconds
+    pub max_p50_ms: Option<f64>,
+    /// Largest 99th percentile latency in milliseconds
+    pub max_p99_ms: Option<f64>,
+}
+
+fn default_targets() -> Vec<String> {
+    vec![String::from(cli::DEFAULT_URL)]
+}
+
+fn default_workers() -> usize {
+    load::DEFAULT_WORKERS
+}
+
+// ========================================================================== //
+
+impl Scenario {
+    /// Read a scenario from a JSON file
+    pub fn load(path: &str) -> Result<Scenario, String> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) => return Err(format!("Failed to read scenario {} ({})", path, e)),
+        };
+        Scenario::from_json(&json)
+    }
+
+    /// Parse and validate a scenario
+    pub fn from_json(json: &str) -> Result<Scenario, String> {
+        let scenario: Scenario = match serde_json::from_str(json) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("Invalid scenario ({})", e)),
+        };
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    /// Check that the scenario describes a run that can be executed
+    fn validate(&self) -> Result<(), String> {
+        if let Err(e) = self.make_targets() {
+            return Err(format!("Invalid targets in scenario ({})", e));
+        }
+        if self.workers == 0 {
+            return Err(format!("Scenario needs at least one worker"));
+        }
+        if self.count.is_none() && self.duration.is_none() {
+            return Err(format!("Scenario needs a count or a duration"));
+        }
+        if self.rate.is_some() && self.duration.is_none() {
+            return Err(format!("Scenario with a rate needs a duration"));
+        }
+        for &(name, v) in &[
+            ("rate", self.rate.unwrap_or(1.0)),
+            ("duration", self.duration.unwrap_or(0.0)),
+            ("rampUp", self.ramp_up),
+            ("rampDown", self.ramp_down),
+            ("connectTimeout", self.connect_timeout.unwrap_or(0.0)),
+            ("timeout", self.timeout.unwrap_or(0.0)),
+            ("mix.register", self.mix.register),
+            ("mix.transfer", self.mix.transfer),
+            ("mix.invalid", self.mix.invalid),
+        ] {
+            if !(v >= 0.0) || !v.is_finite() {
+                return Err(format!("Invalid value for {} in scenario", name));
+            }
+        }
+        if self.mix.register + self.mix.transfer + self.mix.invalid <= 0.0 {
+            return Err(format!("Scenario mix is empty"));
+        }
+        Ok(())
+    }
+
+    /// Returns the rate profile of the scenario, if it has a rate
+    pub fn profile(&self) -> Option<RateProfile> {
+        self.rate.map(|rate| RateProfile {
+            rate,
+            ramp_up: Duration::from_secs_f64(self.ramp_up),
+            hold: Duration::from_secs_f64(self.duration.unwrap_or(0.0)),
+            ramp_down: Duration::from_secs_f64(self.ramp_down),
+        })
+    }
+
+    /// Returns the targets of the scenario with their strategy
+    pub fn make_targets(&self) -> Result<Targets, String> {
+        let targets = Targets::new(self.targets.clone(), self.weights.clone(), self.strategy)?
+            .with_formats(self.formats.clone())?;
+        Ok(targets.with_seed(self.seed))
+    }
+
+    /// Returns the configuration of the REST client of the scenario
+    pub fn rest_config(&self) -> RestConfig {
+        let default = RestConfig::default();
+        RestConfig {
+            connect_timeout: self
+                .connect_timeout
+                .map(Duration::from_secs_f64)
+                .unwrap_or(default.connect_timeout),
+            timeout: self
+                .timeout
+                .map(Duration::from_secs_f64)
+                .unwrap_or(default.timeout),
+            retries: self.retries.unwrap_or(default.retries),
+        }
+    }
+
+    /// Returns the load configuration of the scenario
+    pub fn load_config(&self) -> Result<LoadConfig, String> {
+        let client = match RestClient::new(&self.rest_config()) {
+            Ok(client) => client,
+            Err(e) => return Err(format!("Failed to create HTTP client ({})", e)),
+        };
+        Ok(LoadConfig {
+            targets: self.make_targets()?,
+            client,
+            workers: self.workers,
+            profile: self.profile(),
+            duration: self.duration.map(Duration::from_secs_f64),
+            decoding: self.decoding,
+        })
+    }
+
+    /// Create the workload of the scenario
+    pub fn workload(&self, names: Vec<String>) -> Box<dyn Workload> {
+        Box::new(MixedWorkload::new(
+            names,
+            self.mix.clone(),
+            self.count,
+            self.signing,
+            Entropy::new(self.seed, self.clock),
+        ))
+    }
+
+    /// Check the assertions of the scenario against the statistics of a run.
+    /// Returns a description of each assertion that failed.
+    pub fn check(&self, stats: &RunStats) -> Vec<String> {
+        let a = &self.assertions;
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        let mut failures = Vec::new();
+        if let Some(min) = a.min_accepted {
+            if stats.accepted < min {
+                failures.push(format!("accepted {} < {}", stats.accepted, min));
+            }
+        }
+        if let Some(max) = a.max_rejected {
+            if stats.rejected > max {
+                failures.push(format!("rejected {} > {}", stats.rejected, max));
+            }
+        }
+        if let Some(max) = a.max_errors {
+            if stats.errors > max {
+                failures.push(format!("errors {} > {}", stats.errors, max));
+            }
+        }
+        let max = a.max_mismatches.unwrap_or(0);
+        if stats.mismatches > max {
+            failures.push(format!(
+                "unexpected verdicts {} > {}",
+                stats.mismatches, max
+            ));
+        }
+        if let Some(min) = a.min_throughput {
+            if stats.throughput() < min {
+                failures.push(format!(
+                    "throughput {:.1} tx/s < {} tx/s",
+                    stats.throughput(),
+                    min
+                ));
+            }
+        }
+        if let Some(max) = a.max_p50_ms {
+            let p50 = ms(stats.latency.percentile(50.0));
+            if p50 > max {
+                failures.push(format!("p50 latency {:.1} ms > {} ms", p50, max));
+            }
+        }
+        if let Some(max) = a.max_p99_ms {
+            let p99 = ms(stats.latency.percentile(99.0));
+            if p99 > max {
+                failures.push(format!("p99 latency {:.1} ms > {} ms", p99, max));
+            }
+        }
+        failures
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scenario() {
+        let s = Scenario::from_json(
+            r#"{
+                "name": "smoke",
+                "rate": 10,
+                "duration": 5,
+                "mix": { "transfer": 2 },
+                "assertions": { "maxErrors": 0 }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(s.targets, default_targets());
+        assert_eq!(s.mix.register, 1.0);
+        assert_eq!(s.mix.transfer, 2.0);
+        assert_eq!(s.profile().unwrap().total_count(), 50);
+        assert_eq!(s.assertions.max_errors, Some(0));
+        assert_eq!(s.strategy, Strategy::RoundRobin);
+
+        // Unknown fields and runs without an end are rejected
+        assert!(Scenario::from_json(r#"{ "count": 1, "speed": 2 }"#).is_err());
+        assert!(Scenario::from_json(r#"{ "workers": 2 }"#).is_err());
+
+        // Weights must match the targets
+        let s = r#"{ "count": 1, "targets": ["a", "b"], "strategy": "weighted", "weights": [1] }"#;
+        assert!(Scenario::from_json(s).is_err());
+        let s = r#"{ "count": 1, "targets": ["a", "b"], "formats": ["binary"] }"#;
+        let targets = Scenario::from_json(s).unwrap().make_targets().unwrap();
+        assert_eq!(targets.formats(), &[Format::Binary, Format::Binary][..]);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::hash::{Hash, Hashable};
+use crate::load::SendResult;
+use crate::transaction::{self, Timestamp, Transaction};
+use crate::wallet::Wallet;
+use base64::{decode_config, encode_config};
+use rust_sodium::crypto::sign::ed25519::SecretKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+
+// ========================================================================== //
+
+/// Version of the session file format
+pub const SESSION_VERSION: u32 = 1;
+
+// ========================================================================== //
+
+/// Transaction that the client sent to a node, with the response
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Record {
+    /// Kind of transaction, as shown in the history
+    pub label: String,
+    /// Transaction that was sent
+    #[serde(with = "tx_json")]
+    pub transaction: Transaction,
+    /// Body that was sent instead of the transaction, if any
+    #[serde(default)]
+    pub body: Option<String>,
+    /// URL the transaction was sent to
+    pub url: String,
+    /// Status code of the response
+    #[serde(default)]
+    pub status: Option<u16>,
+    /// Body of the response
+    #[serde(default)]
+    pub response: Option<String>,
+    /// Error if the request failed without a response
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Milliseconds from sending the request until the response was received
+    pub latency_ms: f64,
+    /// Unix time when the response was received
+    pub time: Timestamp,
+    /// Description of how the response differs from the expected verdict
+    #[serde(default)]
+    pub mismatch: Option<String>,
+}
+
+impl Record {
+    /// Create the record of a transaction that has been sent
+    pub fn from_result(result: &SendResult) -> Record {
+        let (status, response, error) = match result.response {
+            Ok((ref body, status)) => (Some(status), Some(body.clone()), None),
+            Err(ref e) => (None, None, Some(e.to_string())),
+        };
+        Record {
+            label: result.job.label.clone(),
+            transaction: result.job.tx.clone(),
+            body: result.job.body.clone(),
+            url: result.url.clone(),
+            status,
+            response,
+            error,
+            latency_ms: result.latency.as_secs_f64() * 1000.0,
+            time: transaction::make_timestamp(),
+            mismatch: result.mismatch.clone(),
+        }
+    }
+}
+
+/// Secret key in a session file
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct KeyRecord {
+    /// Transaction with the public key of the secret key as output
+    #[serde(with = "tx_json")]
+    transaction: Transaction,
+    /// Secret key as URL-safe base64
+    secret_key: String,
+}
+
+/// Contents of a session file
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct SessionFile {
+    version: u32,
+    records: Vec<Record>,
+    keys: Vec<KeyRecord>,
+}
+
+// ========================================================================== //
+
+/// Work of the client that can be saved to disk and resumed later: every
+/// transaction that was sent, and the secret keys of all outputs the client
+/// has created
+#[derive(Clone, Debug, Default)]
+pub struct Session {
+    /// Sent transactions, oldest first
+    pub records: Vec<Record>,
+    /// Secret keys of the outputs
+    pub wallet: Wallet,
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    /// Read a session from a JSON file
+    pub fn load(path: &str) -> Result<Session, String> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) => return Err(format!("Failed to read session {} ({})", path, e)),
+        };
+        Session::from_json(&json)
+    }
+
+    /// Write the session to a JSON file
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        match fs::write(path, self.to_json()) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Failed to write session {} ({})", path, e)),
+        }
+    }
+
+    /// Parse a session from JSON
+    pub fn from_json(json: &str) -> Result<Session, String> {
+        let file: SessionFile = match serde_json::from_str(json) {
+            Ok(file) => file,
+            Err(e) => return Err(format!("Invalid session ({})", e)),
+        };
+        if file.version != SESSION_VERSION {
+            return Err(format!("Unsupported session version {}", file.version));
+        }
+        let mut wallet = Wallet::new();
+        for key in file.keys {
+            let bytes = decode_config(&key.secret_key, base64::URL_SAFE).ok();
+            match bytes.and_then(|b| SecretKey::from_slice(&b)) {
+                Some(sk) => wallet.insert(&key.transaction, sk),
+                None => {
+                    let id = key.transaction.get_id();
+                    return Err(format!("Invalid session (bad secret key for '{}')", id));
+                }
+            }
+        }
+        Ok(Session {
+            records: file.records,
+            wallet,
+        })
+    }
+
+    /// Returns the transactions of a bike that a node accepted, oldest first.
+    /// A transaction that several nodes accepted is only returned once.
+    pub fn accepted(&self, id: &str) -> Vec<Transaction> {
+        let mut seen: HashSet<Hash> = HashSet::new();
+        self.records
+            .iter()
+            .filter(|r| r.transaction.get_id() == id)
+            .filter(|r| r.status.map(|s| (200..300).contains(&s)).unwrap_or(false))
+            .filter(|r| seen.insert(r.transaction.calc_hash()))
+            .map(|r| r.transaction.clone())
+            .collect()
+    }
+
+    /// Returns the session as JSON
+    pub fn to_json(&self) -> String {
+        let keys = self
+            .wallet
+            .entries()
+            .into_iter()
+            .map(|entry| KeyRecord {
+                transaction: entry.tx.clone(),
+                secret_key: encode_config(&entry.secret_key.0[..], base64::URL_SAFE),
+            })
+            .collect();
+        let file = SessionFile {
+            version: SESSION_VERSION,
+            records: self.records.clone(),
+            keys,
+        };
+        serde_json::to_string_pretty(&file).expect("Failed to convert session to JSON")
+    }
+}
+
+// ========================================================================== //
+
+/// Serialize transactions as their JSON object, which is the same as the
+/// body that is sent to the node
+mod tx_json {
+    use crate::transaction::Transaction;
+    use serde::de::{self, Deserialize, Deserializer};
+    use serde::ser::{self, Serialize, Serializer};
+    use serde_json::Value;
+
+    pub fn serialize<S: Serializer>(tx: &Transaction, serializer: S) -> Result<S::Ok, S::Error> {
+        let v: Value = serde_json::from_str(&tx.to_json()).map_err(ser::Error::custom)?;
+        v.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Transaction, D::Error> {
+        let v = Value::deserialize(deserializer)?;
+        Transaction::from_json(&v.to_string()).map_err(de::Error::custom)
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rest::RestError;
+    use crate::workload::Job;
+    use std::time::Duration;
+
+    #[test]
+    fn test_session_round_trip() {
+        let (t0, sk0) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let mut session = Session::new();
+        session.wallet.insert(&t0, sk0);
+        let job = Job::new("register", t0.clone(), None);
+        let response = Ok((String::from("{}"), 200));
+        let mut result = SendResult::debug_new(job, response, Duration::from_millis(12));
+        session.records.push(Record::from_result(&result));
+        result.response = Err(RestError::Timeout(String::from("timed out")));
+        session.records.push(Record::from_result(&result));
+
+        let loaded = Session::from_json(&session.to_json()).unwrap();
+        assert_eq!(loaded.records.len(), 2);
+        assert_eq!(loaded.records[0].transaction.to_json(), t0.to_json());
+        assert_eq!(loaded.records[0].status, Some(200));
+        assert_eq!(loaded.records[0].latency_ms, 12.0);
+        assert!(loaded.records[1].error.is_some());
+
+        // The secret keys can still sign the next transfer
+        let entry = loaded.wallet.latest("SN1337BIKE").unwrap();
+        let (t1, _) = Transaction::debug_make_transfer(&entry.tx, &entry.secret_key);
+        assert_eq!(t1.verify_is_next(&t0), true);
+
+        let json = session
+            .to_json()
+            .replace("\"version\": 1", "\"version\": 2");
+        assert!(Session::from_json(&json).is_err());
+    }
+}
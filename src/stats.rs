@@ -0,0 +1,370 @@
+use crate::load::SendResult;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::time::{Duration, Instant};
+
+// ========================================================================== //
+
+/// Number of linear sub-buckets per power of two in the histogram. Values are
+/// recorded with a relative error of at most 1/32.
+const SUB_BUCKETS: u64 = 64;
+
+/// Number of bits in SUB_BUCKETS
+const SUB_BUCKET_BITS: u32 = 6;
+
+// ========================================================================== //
+
+/// Histogram of latencies with logarithmically sized buckets. Values below
+/// SUB_BUCKETS microseconds are recorded exactly, larger values are recorded in
+/// buckets that are 1/32 of the power of two they belong to.
+#[derive(Clone, Debug, Default)]
+pub struct Histogram {
+    /// Number of values in each bucket
+    counts: Vec<u64>,
+    /// Total number of values
+    count: u64,
+    /// Sum of all values (microseconds)
+    sum: u64,
+    /// Largest value (microseconds)
+    max: u64,
+}
+
+impl Histogram {
+    pub fn new() -> Histogram {
+        Histogram::default()
+    }
+
+    /// Record a duration
+    pub fn record(&mut self, d: Duration) {
+        let us = d.as_micros().min(u64::max_value() as u128) as u64;
+        let idx = bucket_index(us);
+        if idx >= self.counts.len() {
+            self.counts.resize(idx + 1, 0);
+        }
+        self.counts[idx] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(us);
+        self.max = self.max.max(us);
+    }
+
+    /// Returns the number of recorded values
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the largest recorded value
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max)
+    }
+
+    /// Returns the mean of the recorded values
+    pub fn mean(&self) -> Duration {
+        match self.count {
+            0 => Duration::from_secs(0),
+            n => Duration::from_micros(self.sum / n),
+        }
+    }
+
+    /// Returns the value below which 'p' percent of the recorded values are.
+    /// The value is the upper bound of the bucket that the percentile falls
+    /// in, but never larger than the largest recorded value.
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.count == 0 {
+            return Duration::from_secs(0);
+        }
+        let rank = ((p / 100.0) * self.count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (idx, n) in self.counts.iter().enumerate() {
+            seen += n;
+            if seen >= rank {
+                return Duration::from_micros(bucket_upper(idx).min(self.max));
+            }
+        }
+        self.max()
+    }
+
+    /// Merge the values of another histogram into this one
+    pub fn merge(&mut self, other: &Histogram) {
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (idx, n) in other.counts.iter().enumerate() {
+            self.counts[idx] += n;
+        }
+        self.count += other.count;
+        self.sum = self.sum.saturating_add(other.sum);
+        self.max = self.max.max(other.max);
+    }
+}
+
+/// Returns the index of the bucket for a value
+fn bucket_index(v: u64) -> usize {
+    if v < SUB_BUCKETS {
+        return v as usize;
+    }
+    let bits = 64 - v.leading_zeros();
+    let shift = bits - SUB_BUCKET_BITS;
+    let half = SUB_BUCKETS / 2;
+    (SUB_BUCKETS + (shift as u64 - 1) * half + ((v >> shift) - half)) as usize
+}
+
+/// Returns the largest value that is recorded in a bucket
+fn bucket_upper(idx: usize) -> u64 {
+    let idx = idx as u64;
+    if idx < SUB_BUCKETS {
+        return idx;
+    }
+    let half = SUB_BUCKETS / 2;
+    let shift = (idx - SUB_BUCKETS) / half + 1;
+    let sub = (idx - SUB_BUCKETS) % half + half;
+    (sub << shift) + ((1 << shift) - 1)
+}
+
+// ========================================================================== //
+
+/// Statistics of a load run
+#[derive(Clone, Debug)]
+pub struct RunStats {
+    /// Time when the run started
+    start: Instant,
+    /// Time when the run finished
+    end: Option<Instant>,
+    /// Number of transactions sent
+    pub sent: u64,
+    /// Number of transactions that the node accepted
+    pub accepted: u64,
+    /// Number of transactions that the node rejected
+    pub rejected: u64,
+    /// Number of requests that failed without a response
+    pub errors: u64,
+    /// Number of responses that differed from the expected verdict
+    pub mismatches: u64,
+    /// Number of responses for each status code
+    pub statuses: BTreeMap<u16, u64>,
+    /// Number of failed requests for each kind of failure
+    pub failures: BTreeMap<&'static str, u64>,
+    /// Latency of all requests, from the scheduled send time in a run with a
+    /// rate profile
+    pub latency: Histogram,
+    /// Time that scheduled requests waited for a worker after their scheduled
+    /// send time
+    pub lag: Histogram,
+    /// Statistics of each node, indexed by transaction endpoint
+    pub nodes: BTreeMap<String, RunStats>,
+}
+
+impl RunStats {
+    pub fn new() -> RunStats {
+        RunStats::starting_at(Instant::now())
+    }
+
+    fn starting_at(start: Instant) -> RunStats {
+        RunStats {
+            start,
+            end: None,
+            sent: 0,
+            accepted: 0,
+            rejected: 0,
+            errors: 0,
+            mismatches: 0,
+            statuses: BTreeMap::new(),
+            failures: BTreeMap::new(),
+            latency: Histogram::new(),
+            lag: Histogram::new(),
+            nodes: BTreeMap::new(),
+        }
+    }
+
+    /// Record the result of sending a transaction, both for the run and for
+    /// the node it was sent to
+    pub fn record(&mut self, result: &SendResult) {
+        self.count(result);
+        let start = self.start;
+        self.nodes
+            .entry(result.url.clone())
+            .or_insert_with(|| RunStats::starting_at(start))
+            .count(result);
+    }
+
+    /// Count the result of sending a transaction
+    fn count(&mut self, result: &SendResult) {
+        self.sent += 1;
+        self.latency.record(result.latency);
+        if result.job.scheduled.is_some() {
+            self.lag.record(result.lag);
+        }
+        match result.response {
+            Ok((_, status)) => {
+                *self.statuses.entry(status).or_insert(0) += 1;
+                match result.is_accepted() {
+                    true => self.accepted += 1,
+                    false => self.rejected += 1,
+                }
+            }
+            Err(ref e) => {
+                self.errors += 1;
+                *self.failures.entry(e.kind()).or_insert(0) += 1;
+            }
+        }
+        if result.mismatch.is_some() {
+            self.mismatches += 1;
+        }
+    }
+
+    /// Mark the run as finished
+    pub fn finish(&mut self) {
+        if self.end.is_none() {
+            self.end = Some(Instant::now());
+        }
+        let end = self.end;
+        for node in self.nodes.values_mut() {
+            node.end = end;
+        }
+    }
+
+    /// Returns the time since the start of the run, or the duration of the run
+    /// if it has finished
+    pub fn elapsed(&self) -> Duration {
+        match self.end {
+            Some(end) => end - self.start,
+            None => self.start.elapsed(),
+        }
+    }
+
+    /// Returns the number of transactions sent per second
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed().as_secs_f64();
+        match secs > 0.0 {
+            true => self.sent as f64 / secs,
+            false => 0.0,
+        }
+    }
+}
+
+/// Print the statistics as a short multi-line summary, with a line for each
+/// node if the run had several
+impl Display for RunStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        writeln!(
+            f,
+            "sent {} transactions in {:.2} s ({:.1} tx/s)",
+            self.sent,
+            self.elapsed().as_secs_f64(),
+            self.throughput()
+        )?;
+        write!(
+            f,
+            "accepted {}, rejected {}, failed {}, unexpected {}",
+            self.accepted, self.rejected, self.errors, self.mismatches
+        )?;
+        for (status, n) in &self.statuses {
+            write!(f, ", code {}: {}", status, n)?;
+        }
+        for (kind, n) in &self.failures {
+            write!(f, ", {} errors: {}", kind, n)?;
+        }
+        writeln!(f)?;
+        write!(
+            f,
+            "latency p50 {:.1} ms, p90 {:.1} ms, p99 {:.1} ms, max {:.1} ms",
+            ms(self.latency.percentile(50.0)),
+            ms(self.latency.percentile(90.0)),
+            ms(self.latency.percentile(99.0)),
+            ms(self.latency.max())
+        )?;
+        if self.lag.count() > 0 {
+            write!(
+                f,
+                "\nschedule lag p50 {:.1} ms, p90 {:.1} ms, p99 {:.1} ms, max {:.1} ms",
+                ms(self.lag.percentile(50.0)),
+                ms(self.lag.percentile(90.0)),
+                ms(self.lag.percentile(99.0)),
+                ms(self.lag.max())
+            )?;
+        }
+        if self.nodes.len() > 1 {
+            for (url, node) in &self.nodes {
+                write!(
+                    f,
+                    "\n{}: sent {}, accepted {}, rejected {}, failed {}, unexpected {}, \
+                     p50 {:.1} ms, p99 {:.1} ms",
+                    url,
+                    node.sent,
+                    node.accepted,
+                    node.rejected,
+                    node.errors,
+                    node.mismatches,
+                    ms(node.latency.percentile(50.0)),
+                    ms(node.latency.percentile(99.0))
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transaction::Transaction;
+    use crate::workload::Job;
+
+    #[test]
+    fn test_bucket_bounds() {
+        for v in (0..100_000).chain(vec![u64::max_value() / 2, u64::max_value()]) {
+            let idx = bucket_index(v);
+            assert!(bucket_upper(idx) >= v);
+            if idx > 0 {
+                assert!(bucket_upper(idx - 1) < v);
+            }
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut h = Histogram::new();
+        for ms in 1..=1000 {
+            h.record(Duration::from_millis(ms));
+        }
+        assert_eq!(h.count(), 1000);
+        assert_eq!(h.max(), Duration::from_millis(1000));
+        assert_eq!(h.mean(), Duration::from_micros(500_500));
+
+        // Percentiles are within the precision of the buckets
+        for &(p, expected) in &[(50.0, 500.0), (90.0, 900.0), (99.0, 990.0)] {
+            let v = h.percentile(p).as_secs_f64() * 1000.0;
+            assert!(v >= expected && v <= expected * (1.0 + 1.0 / 32.0));
+        }
+        assert_eq!(h.percentile(100.0), h.max());
+    }
+
+    #[test]
+    fn test_schedule_lag() {
+        let (tx, _) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let result = |scheduled: bool, lag_ms| {
+            let mut job = Job::new("register", tx.clone(), None);
+            job.scheduled = match scheduled {
+                true => Some(Instant::now()),
+                false => None,
+            };
+            let response = Ok((String::new(), 200));
+            SendResult {
+                lag: Duration::from_millis(lag_ms),
+                ..SendResult::debug_new(job, response, Duration::from_millis(lag_ms + 5))
+            }
+        };
+
+        // Only scheduled requests have a lag
+        let mut stats = RunStats::new();
+        stats.record(&result(false, 0));
+        assert!(!stats.to_string().contains("schedule lag"));
+        stats.record(&result(true, 100));
+        stats.record(&result(true, 300));
+        assert_eq!(stats.lag.count(), 2);
+        assert_eq!(stats.lag.max(), Duration::from_millis(300));
+        assert_eq!(stats.latency.max(), Duration::from_millis(305));
+        assert!(stats.to_string().contains("\nschedule lag p50 "));
+    }
+}
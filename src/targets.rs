@@ -0,0 +1,327 @@
+use crate::hash;
+use crate::wire::Format;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+// ========================================================================== //
+
+/// Strategies for distributing transactions over several nodes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+    /// Send to each node in turn
+    RoundRobin,
+    /// Send to a node picked uniformly at random
+    Random,
+    /// Send to a node picked at random, in proportion to its weight
+    Weighted,
+    /// Send all transactions of a bike to the same node
+    Sticky,
+    /// Send every transaction to all nodes
+    Broadcast,
+}
+
+/// All strategies
+pub const STRATEGIES: [Strategy; 5] = [
+    Strategy::RoundRobin,
+    Strategy::Random,
+    Strategy::Weighted,
+    Strategy::Sticky,
+    Strategy::Broadcast,
+];
+
+impl Strategy {
+    /// Returns the name of the strategy
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::RoundRobin => "round-robin",
+            Strategy::Random => "random",
+            Strategy::Weighted => "weighted",
+            Strategy::Sticky => "sticky",
+            Strategy::Broadcast => "broadcast",
+        }
+    }
+
+    /// Returns a short description of the strategy
+    pub fn description(self) -> &'static str {
+        match self {
+            Strategy::RoundRobin => "Round-robin",
+            Strategy::Random => "Random",
+            Strategy::Weighted => "Weighted random",
+            Strategy::Sticky => "Sticky per bike",
+            Strategy::Broadcast => "Broadcast to all",
+        }
+    }
+}
+
+impl Default for Strategy {
+    fn default() -> Strategy {
+        Strategy::RoundRobin
+    }
+}
+
+impl FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Strategy, String> {
+        match STRATEGIES.iter().find(|st| st.name() == s) {
+            Some(strategy) => Ok(*strategy),
+            None => Err(format!("Unknown strategy '{}'", s)),
+        }
+    }
+}
+
+impl Display for Strategy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// ========================================================================== //
+
+/// Endpoint that a transaction is sent to
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Target<'a> {
+    /// Transaction endpoint
+    pub url: &'a str,
+    /// Format that the endpoint expects transactions in
+    pub format: Format,
+}
+
+/// Transaction endpoints of the nodes of a run, with the strategy that
+/// distributes the transactions over them
+#[derive(Clone, Debug, PartialEq)]
+pub struct Targets {
+    /// Transaction endpoints
+    urls: Vec<String>,
+    /// Weight of each endpoint, for the weighted strategy
+    weights: Vec<f64>,
+    /// Wire format of each endpoint
+    formats: Vec<Format>,
+    /// Strategy for picking the endpoints of a transaction
+    strategy: Strategy,
+    /// Seed of the random picks, or None to pick with the thread generator
+    seed: Option<u64>,
+}
+
+impl Targets {
+    /// Create the targets for a list of endpoints. Without weights, all
+    /// endpoints have the same weight.
+    pub fn new(
+        urls: Vec<String>,
+        weights: Option<Vec<f64>>,
+        strategy: Strategy,
+    ) -> Result<Targets, String> {
+        if urls.is_empty() {
+            return Err(format!("At least one target is needed"));
+        }
+        let weights = weights.unwrap_or_else(|| vec![1.0; urls.len()]);
+        if weights.len() != urls.len() {
+            return Err(format!(
+                "Number of weights ({}) does not match the number of targets ({})",
+                weights.len(),
+                urls.len()
+            ));
+        }
+        if weights.iter().any(|w| !(*w >= 0.0) || !w.is_finite()) {
+            return Err(format!("Weights must be finite and not negative"));
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Err(format!("At least one weight must be greater than 0"));
+        }
+        let formats = vec![Format::default(); urls.len()];
+        Ok(Targets {
+            urls,
+            weights,
+            formats,
+            strategy,
+            seed: None,
+        })
+    }
+
+    /// Set the seed of the random and weighted strategies. With a seed, the
+    /// pick for a transaction only depends on the seed and its sequence
+    /// number, so a run with the same seed sends to the same nodes.
+    pub fn with_seed(mut self, seed: Option<u64>) -> Targets {
+        self.seed = seed;
+        self
+    }
+
+    /// Set the wire format of each endpoint. A single format is used for all
+    /// endpoints and without formats, all endpoints use JSON.
+    pub fn with_formats(mut self, formats: Option<Vec<Format>>) -> Result<Targets, String> {
+        let n = self.urls.len();
+        self.formats = match formats {
+            Some(ref formats) if formats.len() == 1 => vec![formats[0]; n],
+            Some(formats) if formats.len() == n => formats,
+            Some(formats) => {
+                return Err(format!(
+                    "Number of formats ({}) does not match the number of targets ({})",
+                    formats.len(),
+                    n
+                ))
+            }
+            None => vec![Format::default(); n],
+        };
+        Ok(self)
+    }
+
+    /// Create the targets from lists of endpoints and weights that are
+    /// separated by commas or whitespace. An empty list of weights gives all
+    /// endpoints the same weight.
+    pub fn parse(urls: &str, weights: &str, strategy: Strategy) -> Result<Targets, String> {
+        let urls: Vec<String> = split_list(urls).map(String::from).collect();
+        let weights = match split_list(weights).next() {
+            Some(_) => {
+                let mut list = Vec::new();
+                for w in split_list(weights) {
+                    match w.parse::<f64>() {
+                        Ok(w) => list.push(w),
+                        Err(e) => return Err(format!("Invalid weight '{}' ({})", w, e)),
+                    }
+                }
+                Some(list)
+            }
+            None => None,
+        };
+        Targets::new(urls, weights, strategy)
+    }
+
+    /// Returns the endpoints
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Returns the weights of the endpoints
+    pub fn weights(&self) -> &[f64] {
+        &self.weights
+    }
+
+    /// Returns the wire formats of the endpoints
+    pub fn formats(&self) -> &[Format] {
+        &self.formats
+    }
+
+    /// Returns the strategy
+    pub fn strategy(&self) -> Strategy {
+        self.strategy
+    }
+
+    /// Returns the endpoints to send a transaction to. 'seq' is the sequence
+    /// number of the transaction in the run and 'id' is the id of its bike.
+    pub fn pick(&self, seq: usize, id: &str) -> Vec<Target<'_>> {
+        let n = self.urls.len();
+        let idx = match self.strategy {
+            Strategy::Broadcast => return (0..n).map(|idx| self.target(idx)).collect(),
+            _ if n == 1 => 0,
+            Strategy::RoundRobin => seq % n,
+            Strategy::Random => ((self.gen_unit(seq) * n as f64) as usize).min(n - 1),
+            Strategy::Weighted => {
+                let total: f64 = self.weights.iter().sum();
+                let mut pick = self.gen_unit(seq) * total;
+                let mut idx = 0;
+                while idx + 1 < n && pick >= self.weights[idx] {
+                    pick -= self.weights[idx];
+                    idx += 1;
+                }
+                idx
+            }
+            Strategy::Sticky => {
+                let h = hash::obj_hash(&id);
+                let mut v = [0u8; 8];
+                v.copy_from_slice(&h[..8]);
+                (u64::from_le_bytes(v) % n as u64) as usize
+            }
+        };
+        vec![self.target(idx)]
+    }
+
+    /// Returns a random number in [0, 1) for the transaction with a sequence
+    /// number, from the seed if there is one
+    fn gen_unit(&self, seq: usize) -> f64 {
+        match self.seed {
+            Some(seed) => {
+                let mut bytes = seed.to_le_bytes().to_vec();
+                bytes.extend_from_slice(&(seq as u64).to_le_bytes());
+                StdRng::from_seed(hash::obj_hash(&bytes)).gen()
+            }
+            None => rand::thread_rng().gen(),
+        }
+    }
+
+    /// Returns the endpoint at an index
+    pub fn target(&self, idx: usize) -> Target<'_> {
+        Target {
+            url: &self.urls[idx],
+            format: self.formats[idx],
+        }
+    }
+}
+
+impl Display for Targets {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.urls.join(", "))
+    }
+}
+
+/// Split a list that is separated by commas or whitespace
+fn split_list(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_targets() {
+        let urls = vec![
+            format!("http://a"),
+            format!("http://b"),
+            format!("http://c"),
+        ];
+        let make = |strategy, weights| Targets::new(urls.clone(), weights, strategy).unwrap();
+
+        let t = make(Strategy::RoundRobin, None);
+        let picks: Vec<&str> = (0..4).map(|seq| t.pick(seq, "Bike")[0].url).collect();
+        assert_eq!(picks, vec!["http://a", "http://b", "http://c", "http://a"]);
+
+        let t = make(Strategy::Sticky, None);
+        let first = t.pick(0, "Bike");
+        assert!((1..10).all(|seq| t.pick(seq, "Bike") == first));
+
+        let t = make(Strategy::Weighted, Some(vec![0.0, 1.0, 0.0]));
+        assert!((0..10).all(|seq| t.pick(seq, "Bike")[0].url == "http://b"));
+
+        // Seeded random picks are the same in every run
+        let picks = |seed| {
+            let t = make(Strategy::Random, None).with_seed(Some(seed));
+            let picks: Vec<&str> = (0..20).map(|seq| t.pick(seq, "Bike")[0].url).collect();
+            picks.join(" ")
+        };
+        assert_eq!(picks(42), picks(42));
+        assert!(picks(42) != picks(43));
+
+        let t = make(Strategy::Broadcast, None)
+            .with_formats(Some(vec![Format::Binary]))
+            .unwrap();
+        let picks = t.pick(0, "Bike");
+        assert_eq!(picks.len(), 3);
+        assert!(picks.iter().all(|p| p.format == Format::Binary));
+    }
+
+    #[test]
+    fn test_parse_targets() {
+        let t = Targets::parse("http://a, http://b", "", Strategy::Random).unwrap();
+        assert_eq!(t.urls().len(), 2);
+        assert!(Targets::parse("http://a http://b", "1", Strategy::Weighted).is_err());
+        assert!(Targets::parse("", "", Strategy::RoundRobin).is_err());
+        assert!(t.with_formats(Some(vec![Format::Json; 3])).is_err());
+        assert_eq!("sticky".parse::<Strategy>(), Ok(Strategy::Sticky));
+    }
+}
@@ -1,22 +1,174 @@
+use crate::entropy::Entropy;
 use crate::hash::{self, Hash, Hashable};
-use base64::{decode_config, encode_config};
+use crate::keys::{KeyError, PubKey, Signature};
+use base64::DecodeError;
 use rust_sodium::crypto::sign::{
-    self, ed25519, ed25519::sign_detached, ed25519::verify_detached, ed25519::PublicKey,
-    ed25519::SecretKey,
+    self, ed25519::sign_detached, ed25519::verify_detached, ed25519::SecretKey,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::{self, json, Value};
 use std::fmt::{self, Display, Formatter};
 use std::iter::repeat;
+use std::str::FromStr;
 use std::time::SystemTime;
 // ========================================================================== //
 
-pub type PubKey = Vec<u8>;
-pub type Signature = Vec<u8>;
+/// Tag at the start of a versioned signing payload, which separates the
+/// signatures of transactions from signatures over any other data
+const SIGNING_DOMAIN: &[u8] = b"sim_client/transaction";
+
+/// Type byte of a registration in a versioned signing payload
+const TYPE_REGISTER: u8 = 0;
+
+/// Type byte of a transfer in a versioned signing payload
+const TYPE_TRANSFER: u8 = 1;
+
+// ========================================================================== //
+
+/// Schemes for the bytes that are signed for a transaction
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum SigningScheme {
+    /// Fields concatenated without lengths, as understood by all nodes
+    Legacy,
+    /// Domain tag, version, type byte and length-prefixed fields
+    V1,
+}
+
+/// All signing schemes
+pub const SIGNING_SCHEMES: [SigningScheme; 2] = [SigningScheme::Legacy, SigningScheme::V1];
+
+impl SigningScheme {
+    /// Returns the name of the scheme
+    pub fn name(self) -> &'static str {
+        match self {
+            SigningScheme::Legacy => "legacy",
+            SigningScheme::V1 => "v1",
+        }
+    }
+
+    /// Returns a short description of the scheme
+    pub fn description(self) -> &'static str {
+        match self {
+            SigningScheme::Legacy => "Legacy signing",
+            SigningScheme::V1 => "Signing v1",
+        }
+    }
+
+    /// Returns the version number of the scheme, which is 0 for the legacy
+    /// scheme
+    pub fn version(self) -> u8 {
+        match self {
+            SigningScheme::Legacy => 0,
+            SigningScheme::V1 => 1,
+        }
+    }
+
+    /// Returns the scheme with a version number
+    pub fn from_version(version: u64) -> Option<SigningScheme> {
+        SIGNING_SCHEMES
+            .iter()
+            .find(|s| u64::from(s.version()) == version)
+            .cloned()
+    }
+}
+
+impl Default for SigningScheme {
+    fn default() -> SigningScheme {
+        SigningScheme::Legacy
+    }
+}
+
+impl FromStr for SigningScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<SigningScheme, String> {
+        match SIGNING_SCHEMES.iter().find(|sc| sc.name() == s) {
+            Some(scheme) => Ok(*scheme),
+            None => Err(format!("Unknown signing scheme '{}'", s)),
+        }
+    }
+}
+
+impl Display for SigningScheme {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Modes for decoding the JSON of a transaction
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Decoding {
+    /// Ignore unknown fields and read an input key that is not a string as
+    /// no input. Keys and the signature are URL-safe base64.
+    Normal,
+    /// Reject unknown fields and an input key that is neither a string nor
+    /// null
+    Strict,
+    /// Like normal, but also accept keys and the signature in standard
+    /// base64, without padding or in hex
+    Lenient,
+}
+
+/// All decoding modes
+pub const DECODINGS: [Decoding; 3] = [Decoding::Normal, Decoding::Strict, Decoding::Lenient];
+
+/// Fields of the JSON of a transaction
+const JSON_FIELDS: [&str; 6] = [
+    "id",
+    "timestamp",
+    "publicKeyInput",
+    "publicKeyOutput",
+    "signature",
+    "signingVersion",
+];
+
+impl Decoding {
+    /// Returns the name of the mode
+    pub fn name(self) -> &'static str {
+        match self {
+            Decoding::Normal => "normal",
+            Decoding::Strict => "strict",
+            Decoding::Lenient => "lenient",
+        }
+    }
+
+    /// Returns a short description of the mode
+    pub fn description(self) -> &'static str {
+        match self {
+            Decoding::Normal => "Normal decoding",
+            Decoding::Strict => "Strict decoding",
+            Decoding::Lenient => "Lenient decoding",
+        }
+    }
+}
+
+impl Default for Decoding {
+    fn default() -> Decoding {
+        Decoding::Normal
+    }
+}
+
+impl FromStr for Decoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Decoding, String> {
+        match DECODINGS.iter().find(|d| d.name() == s) {
+            Some(decoding) => Ok(*decoding),
+            None => Err(format!("Unknown decoding '{}'", s)),
+        }
+    }
+}
+
+impl Display for Decoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
 
 // ========================================================================== //
 
-/// Future work: PubKey and Signature should be fixed size arrays.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Transaction {
     /// bike SN
@@ -26,6 +178,115 @@ pub struct Transaction {
     pub_key_input: Option<PubKey>,
     pub_key_output: PubKey,
     signature: Signature,
+    /// scheme of the signed bytes
+    scheme: SigningScheme,
+}
+
+// ========================================================================== //
+
+/// Reasons for a signature to fail verification
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum VerifyError {
+    /// The signature does not match the content
+    BadSignature,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::BadSignature => write!(f, "signature is not valid"),
+        }
+    }
+}
+
+/// Errors from parsing and verifying transactions
+#[derive(Clone, Debug, PartialEq)]
+pub enum TxError {
+    /// The input is not valid JSON
+    Json(String),
+    /// The input is not a valid binary transaction
+    Binary(String),
+    /// The object has a field that is not part of a transaction
+    UnknownField(String),
+    /// A field is missing or does not have the expected type
+    Field {
+        /// Name of the field
+        field: &'static str,
+        /// Expected type of the field
+        expected: &'static str,
+    },
+    /// A field could not be decoded from base64
+    Decode {
+        /// Name of the field
+        field: &'static str,
+        /// Offset in the value of the first invalid character, if known
+        offset: Option<usize>,
+        /// Error from the decoder
+        error: String,
+    },
+    /// A key or signature does not have the expected number of bytes
+    Length {
+        /// Name of the field
+        field: &'static str,
+        /// Expected number of bytes
+        expected: usize,
+        /// Number of bytes that were decoded
+        found: usize,
+    },
+    /// The signature could not be verified
+    Verify(VerifyError),
+}
+
+impl TxError {
+    /// Returns the name of the kind of error
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TxError::Json(_) => "json",
+            TxError::Binary(_) => "binary",
+            TxError::UnknownField(_) => "unknown-field",
+            TxError::Field { .. } => "field",
+            TxError::Decode { .. } => "decode",
+            TxError::Length { .. } => "length",
+            TxError::Verify(_) => "verify",
+        }
+    }
+
+    /// Returns the name of the field the error is about, if any
+    pub fn field(&self) -> Option<&str> {
+        match self {
+            TxError::UnknownField(field) => Some(field),
+            TxError::Field { field, .. }
+            | TxError::Decode { field, .. }
+            | TxError::Length { field, .. } => Some(field),
+            TxError::Json(_) | TxError::Binary(_) | TxError::Verify(_) => None,
+        }
+    }
+}
+
+impl Display for TxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TxError::Json(e) => write!(f, "invalid JSON ({})", e),
+            TxError::Binary(e) => write!(f, "invalid binary transaction ({})", e),
+            TxError::UnknownField(field) => write!(f, "unknown field '{}'", field),
+            TxError::Field { field, expected } => {
+                write!(f, "field '{}' is missing or not a {}", field, expected)
+            }
+            TxError::Decode { field, error, .. } => {
+                write!(f, "field '{}' is not valid base64 ({})", field, error)
+            }
+            TxError::Length {
+                field,
+                expected,
+                found,
+            } => write!(
+                f,
+                "field '{}' has {} bytes instead of {}",
+                field, found, expected
+            ),
+            TxError::Verify(e) => write!(f, "{}", e),
+        }
+    }
 }
 
 // ========================================================================== //
@@ -41,17 +302,11 @@ pub fn make_timestamp() -> Timestamp {
 
 // ========================================================================== //
 
-/// Helper function to format a Vec<u8> buffer to string as hex rep.
-pub fn buf_to_str(buf: &Vec<u8>) -> String {
-    let parts: Vec<String> = buf.iter().map(|byte| format!("{:02x}", byte)).collect();
-    parts.join("")
-}
-
 /// Allow transactions to be printed.
 impl Display for Transaction {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let k_input = if self.pub_key_input.is_some() {
-            buf_to_str(&self.pub_key_input.as_ref().unwrap())
+            self.pub_key_input.unwrap().to_hex()
         } else {
             format!("None")
         };
@@ -62,8 +317,8 @@ impl Display for Transaction {
             self.id,
             self.timestamp,
             k_input,
-            buf_to_str(&self.pub_key_output),
-            buf_to_str(&self.signature)
+            self.pub_key_output.to_hex(),
+            self.signature.to_hex()
         )
     }
 }
@@ -75,7 +330,8 @@ impl Transaction {
             timestamp: make_timestamp(),
             pub_key_input: pub_key_input,
             pub_key_output: pub_key_output,
-            signature: Vec::new(),
+            signature: Signature::default(),
+            scheme: SigningScheme::default(),
         }
     }
 
@@ -85,6 +341,7 @@ impl Transaction {
         pub_key_input: Option<PubKey>,
         pub_key_output: PubKey,
         signature: Signature,
+        scheme: SigningScheme,
     ) -> Transaction {
         Transaction {
             id,
@@ -92,30 +349,59 @@ impl Transaction {
             pub_key_input,
             pub_key_output,
             signature,
+            scheme,
         }
     }
 
     /// @param id The id of the item, such as serial number of a bike.
     pub fn debug_make_register(id: String) -> (Transaction, SecretKey) {
-        let (pk, sk) = sign::gen_keypair();
-        let mut t = Transaction::new(id, None, pk.as_ref().to_vec());
+        let mut entropy = Entropy::default();
+        Transaction::debug_make_register_with(id, SigningScheme::default(), &mut entropy)
+    }
+
+    /// @param id The id of the item, such as serial number of a bike.
+    /// @param scheme The scheme of the signed bytes
+    /// @param entropy The source of the key and the timestamp
+    pub fn debug_make_register_with(
+        id: String,
+        scheme: SigningScheme,
+        entropy: &mut Entropy,
+    ) -> (Transaction, SecretKey) {
+        let (pk, sk) = entropy.gen_keypair();
+        let mut t = Transaction::new(id, None, PubKey::from(&pk));
+        t.timestamp = entropy.timestamp();
+        t.scheme = scheme;
         t.sign(&sk);
         (t, sk)
     }
 
+    /// The transfer is signed with the scheme of the previous transaction.
     /// @param t_prev The previous transaction
     /// @param t_sk The previous secret key
     pub fn debug_make_transfer(
         t_prev: &Transaction,
         sk_prev: &SecretKey,
     ) -> (Transaction, SecretKey) {
-        let (pk, sk) = sign::gen_keypair();
+        Transaction::debug_make_transfer_with(t_prev, sk_prev, &mut Entropy::default())
+    }
+
+    /// The transfer is signed with the scheme of the previous transaction.
+    /// @param t_prev The previous transaction
+    /// @param t_sk The previous secret key
+    /// @param entropy The source of the new key and the timestamp
+    pub fn debug_make_transfer_with(
+        t_prev: &Transaction,
+        sk_prev: &SecretKey,
+        entropy: &mut Entropy,
+    ) -> (Transaction, SecretKey) {
+        let (pk, sk) = entropy.gen_keypair();
         let mut t = Transaction {
             id: t_prev.id.clone(),
-            timestamp: make_timestamp(),
-            pub_key_input: Some(t_prev.pub_key_output.clone()),
-            pub_key_output: pk.as_ref().to_vec(),
-            signature: Vec::new(),
+            timestamp: entropy.timestamp(),
+            pub_key_input: Some(t_prev.pub_key_output),
+            pub_key_output: PubKey::from(&pk),
+            signature: Signature::default(),
+            scheme: t_prev.scheme,
         };
         t.sign(&sk_prev);
         (t, sk)
@@ -125,18 +411,18 @@ impl Transaction {
         let bytes: Vec<u8> = repeat(0).take(sign::SEEDBYTES).collect();
         let seed = sign::Seed::from_slice(&bytes).expect("Failed to generate seed");
         let (pk, sk) = sign::keypair_from_seed(&seed);
-        let mut t = Transaction::new(String::from("GENESIS"), None, pk.as_ref().to_vec());
+        let mut t = Transaction::new(String::from("GENESIS"), None, PubKey::from(&pk));
         t.timestamp = 0;
         t.sign(&sk);
         (t, sk)
     }
 
-    /// Sign a transaction. Make sure all data is filled in, except
-    /// signature. Store the signature in itself.
+    /// Sign a transaction with its scheme. Make sure all data is filled in,
+    /// except signature. Store the signature in itself.
     pub(crate) fn sign(&mut self, sk: &SecretKey) {
         let buf = self.content_to_u8();
-        let sig = Vec::from(sign_detached(buf.as_slice(), &sk).as_ref());
-        self.signature = sig;
+        let sig = sign_detached(buf.as_slice(), &sk);
+        self.signature = Signature::from(&sig);
     }
 
     /// Verify that this transaction is a valid next transaction, given that the
@@ -156,44 +442,59 @@ impl Transaction {
     /// There are two types of transactions that are verified differently.
     ///   "Register": There is no input, use the public key of the output.
     ///   "Transfer": There is a input, use the public key of the input.
-    pub fn verify(&self) -> Result<(), String> {
-        let do_verify = |pk: &[u8], sig: &[u8]| -> Result<(), String> {
-            //println!("pk len: {}, sig len: {}", pk.len(), sig.len());
-            let pk = PublicKey::from_slice(pk);
-            let pk = match pk {
-                Some(p) => p,
-                None => return Err(format!("could not create public key from input")),
-            };
-            let sig = match ed25519::Signature::from_slice(sig) {
-                Some(sig) => sig,
-                None => {
-                    return Err(format!("signature has invalid format"));
-                }
-            };
-            match verify_detached(&sig, &self.content_to_u8(), &pk) {
-                true => return Ok(()),
-                false => return Err(format!("signature is not valid")),
-            };
+    pub fn verify(&self) -> Result<(), TxError> {
+        let pk = match &self.pub_key_input {
+            Some(pub_key_input) => pub_key_input,
+            None => &self.pub_key_output,
         };
-
-        match &self.pub_key_input {
-            Some(pub_key_input) => {
-                return do_verify(pub_key_input.as_slice(), self.signature.as_slice());
-            }
-            None => {
-                return do_verify(self.pub_key_output.as_slice(), self.signature.as_slice());
-            }
+        let sig = self.signature.to_sodium();
+        match verify_detached(&sig, &self.content_to_u8(), &pk.to_sodium()) {
+            true => Ok(()),
+            false => Err(TxError::Verify(VerifyError::BadSignature)),
         }
     }
 
-    /// Copy the content of the transaction into a buffer
+    /// Copy the content of the transaction into a buffer, in the format of
+    /// its signing scheme
     fn content_to_u8(&self) -> Vec<u8> {
+        match self.scheme {
+            SigningScheme::Legacy => self.legacy_content(),
+            SigningScheme::V1 => self.versioned_content(),
+        }
+    }
+
+    /// Returns the concatenated fields. Different splits of the fields can
+    /// give the same bytes.
+    fn legacy_content(&self) -> Vec<u8> {
         let mut buf: Vec<u8> = Vec::from(self.id.as_bytes());
         buf.extend_from_slice(&self.timestamp.to_le_bytes());
         if let Some(ref key) = self.pub_key_input {
-            buf.extend(key);
+            buf.extend_from_slice(key.as_bytes());
+        }
+        buf.extend_from_slice(self.pub_key_output.as_bytes());
+        buf
+    }
+
+    /// Returns the domain tag, the version of the scheme, the type byte and
+    /// the fields, where each byte field is prefixed with its length as a
+    /// little-endian u32
+    fn versioned_content(&self) -> Vec<u8> {
+        let push_field = |buf: &mut Vec<u8>, field: &[u8]| {
+            buf.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            buf.extend_from_slice(field);
+        };
+        let mut buf: Vec<u8> = Vec::from(SIGNING_DOMAIN);
+        buf.push(self.scheme.version());
+        buf.push(match self.pub_key_input {
+            Some(_) => TYPE_TRANSFER,
+            None => TYPE_REGISTER,
+        });
+        push_field(&mut buf, self.id.as_bytes());
+        buf.extend_from_slice(&self.timestamp.to_le_bytes());
+        if let Some(ref key) = self.pub_key_input {
+            push_field(&mut buf, key.as_bytes());
         }
-        buf.extend(&self.pub_key_output);
+        push_field(&mut buf, self.pub_key_output.as_bytes());
         buf
     }
 
@@ -203,76 +504,112 @@ impl Transaction {
             "id": self.get_id(),
             "timestamp": self.get_timestamp(),
             "publicKeyInput": Value::Null,
-            "publicKeyOutput": encode_config(self.get_public_key_output(),base64::URL_SAFE),
-            "signature": encode_config(self.get_signature(), base64::URL_SAFE),
+            "publicKeyOutput": self.get_public_key_output().to_base64(),
+            "signature": self.get_signature().to_base64(),
         });
         if let Some(pk) = self.get_public_key_input() {
-            *v.get_mut("publicKeyInput").unwrap() = json!(encode_config(pk, base64::URL_SAFE));
+            *v.get_mut("publicKeyInput").unwrap() = json!(pk.to_base64());
+        }
+        // Nodes that only know the legacy scheme do not expect the field
+        if self.scheme != SigningScheme::Legacy {
+            v["signingVersion"] = json!(self.scheme.version());
         }
         serde_json::to_string_pretty(&v).expect("Failed to convert to json")
     }
 
     ///
-    pub fn from_json(string: &str) -> Result<Self, String> {
+    pub fn from_json(string: &str) -> Result<Self, TxError> {
+        Transaction::from_json_with(string, Decoding::Normal)
+    }
+
+    /// Parse the JSON of a transaction in a decoding mode
+    pub fn from_json_with(string: &str, decoding: Decoding) -> Result<Self, TxError> {
         let v = match serde_json::from_str(string) {
             Ok(v) => v,
-            Err(_) => return Err(format!("Failed to convert from json")),
+            Err(e) => return Err(TxError::Json(e.to_string())),
         };
-        match Transaction::from_json_value(&v) {
-            Ok(tx) => Ok(tx),
-            Err(_) => Err(format!("Could not convert json to Transaction")),
-        }
+        Transaction::from_json_value(&v, decoding)
     }
 
     ///
-    fn from_json_value(v: &Value) -> Result<Self, String> {
+    fn from_json_value(v: &Value, decoding: Decoding) -> Result<Self, TxError> {
+        if decoding == Decoding::Strict {
+            let object = match v.as_object() {
+                Some(object) => object,
+                None => return Err(TxError::Json(format!("transaction is not an object"))),
+            };
+            if let Some(k) = object.keys().find(|k| !JSON_FIELDS.contains(&k.as_str())) {
+                return Err(TxError::UnknownField(k.clone()));
+            }
+        }
+        let (decode_key, decode_signature): (fn(&str) -> _, fn(&str) -> _) = match decoding {
+            Decoding::Lenient => (PubKey::decode_lenient, Signature::decode_lenient),
+            Decoding::Normal | Decoding::Strict => (PubKey::from_base64, Signature::from_base64),
+        };
+
         let id: String = match v["id"].as_str() {
             Some(s) => s.to_string(),
-            None => return Err(format!("Could not parse id as String")),
+            None => {
+                return Err(TxError::Field {
+                    field: "id",
+                    expected: "string",
+                })
+            }
         };
 
         let timestamp: Timestamp = match v["timestamp"].as_u64() {
             Some(v) => v,
-            None => return Err(format!("Could not parse id as u64")),
+            None => {
+                return Err(TxError::Field {
+                    field: "timestamp",
+                    expected: "u64",
+                })
+            }
         };
 
-        let pub_key_input: Option<PubKey> = match v["publicKeyInput"].as_str() {
-            Some(s) => match decode_config(s, base64::URL_SAFE) {
-                Ok(v) => Some(v),
-                Err(e) => {
-                    return Err(format!(
-                        "Could not decode publicKeyInput from base64 with error: {}",
-                        e
-                    ))
-                }
-            },
-            None => None,
+        let pub_key_input: Option<PubKey> = match &v["publicKeyInput"] {
+            Value::String(s) => Some(decode_field("publicKeyInput", s, decode_key)?),
+            Value::Null => None,
+            _ if decoding == Decoding::Strict => {
+                return Err(TxError::Field {
+                    field: "publicKeyInput",
+                    expected: "string or null",
+                })
+            }
+            _ => None,
         };
 
         let pub_key_output: PubKey = match v["publicKeyOutput"].as_str() {
-            Some(s) => match decode_config(s, base64::URL_SAFE) {
-                Ok(v) => v,
-                Err(e) => {
-                    return Err(format!(
-                        "Could not decode publicKeyOutput from base64 with error: {}",
-                        e
-                    ))
-                }
-            },
-            None => return Err(format!("Could not parse publicKeyOutput as String")),
+            Some(s) => decode_field("publicKeyOutput", s, decode_key)?,
+            None => {
+                return Err(TxError::Field {
+                    field: "publicKeyOutput",
+                    expected: "string",
+                })
+            }
         };
 
         let signature: Signature = match v["signature"].as_str() {
-            Some(s) => match decode_config(s, base64::URL_SAFE) {
-                Ok(v) => v,
-                Err(e) => {
-                    return Err(format!(
-                        "Could not decode signature from base64 with error: {}",
-                        e
-                    ))
+            Some(s) => decode_field("signature", s, decode_signature)?,
+            None => {
+                return Err(TxError::Field {
+                    field: "signature",
+                    expected: "string",
+                })
+            }
+        };
+
+        let scheme = match &v["signingVersion"] {
+            Value::Null => SigningScheme::Legacy,
+            version => match version.as_u64().and_then(SigningScheme::from_version) {
+                Some(scheme) => scheme,
+                None => {
+                    return Err(TxError::Field {
+                        field: "signingVersion",
+                        expected: "known signing version",
+                    })
                 }
             },
-            None => return Err(format!("Could not parse signature as String")),
         };
 
         Ok(Transaction::from_details(
@@ -281,6 +618,7 @@ impl Transaction {
             pub_key_input,
             pub_key_output,
             signature,
+            scheme,
         ))
     }
 
@@ -320,6 +658,45 @@ impl Transaction {
     pub fn get_signature(&self) -> &Signature {
         &self.signature
     }
+
+    /// Returns the scheme of the signed bytes
+    pub fn get_scheme(&self) -> SigningScheme {
+        self.scheme
+    }
+}
+
+/// Decode a base64 field of a JSON transaction with the decoder of its type
+fn decode_field<T>(
+    field: &'static str,
+    s: &str,
+    decode: fn(&str) -> Result<T, KeyError>,
+) -> Result<T, TxError> {
+    match decode(s) {
+        Ok(v) => Ok(v),
+        Err(KeyError::Length { expected, found }) => Err(TxError::Length {
+            field,
+            expected,
+            found,
+        }),
+        Err(KeyError::Base64(e)) => {
+            let offset = match e {
+                DecodeError::InvalidByte(offset, _) | DecodeError::InvalidLastSymbol(offset, _) => {
+                    Some(offset)
+                }
+                DecodeError::InvalidLength => None,
+            };
+            Err(TxError::Decode {
+                field,
+                offset,
+                error: e.to_string(),
+            })
+        }
+        Err(e) => Err(TxError::Decode {
+            field,
+            offset: None,
+            error: e.to_string(),
+        }),
+    }
 }
 
 impl Hashable for Transaction {
@@ -342,7 +719,92 @@ mod tests {
 
         // tamper with the transaction content
         t.id += "1";
-        assert_ne!(t.verify(), Ok(()));
+        assert_eq!(t.verify(), Err(TxError::Verify(VerifyError::BadSignature)));
+    }
+
+    #[test]
+    fn test_from_json_errors() {
+        let (t, _) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let mut v: Value = serde_json::from_str(&t.to_json()).unwrap();
+        v["signature"] = json!("not base64!");
+        let e = Transaction::from_json(&v.to_string()).unwrap_err();
+        assert_eq!(e.kind(), "decode");
+        assert_eq!(e.field(), Some("signature"));
+
+        // Keys must have exactly the length of a key
+        v["publicKeyOutput"] = json!(t.get_public_key_output().to_base64()[..24]);
+        let e = Transaction::from_json(&v.to_string()).unwrap_err();
+        assert_eq!(
+            e,
+            TxError::Length {
+                field: "publicKeyOutput",
+                expected: 32,
+                found: 18
+            }
+        );
+
+        // Strict decoding rejects what normal decoding ignores and lenient
+        // decoding accepts other encodings of the keys
+        let mut v: Value = serde_json::from_str(&t.to_json()).unwrap();
+        v["note"] = json!("hello");
+        v["publicKeyInput"] = json!(1);
+        assert!(Transaction::from_json(&v.to_string()).is_ok());
+        let e = Transaction::from_json_with(&v.to_string(), Decoding::Strict).unwrap_err();
+        assert_eq!(e, TxError::UnknownField(format!("note")));
+        v.as_object_mut().unwrap().remove("note");
+        let e = Transaction::from_json_with(&v.to_string(), Decoding::Strict).unwrap_err();
+        assert_eq!(e.field(), Some("publicKeyInput"));
+        v["publicKeyInput"] = Value::Null;
+        v["publicKeyOutput"] = json!(t.get_public_key_output().to_hex());
+        assert!(Transaction::from_json(&v.to_string()).is_err());
+        let lenient = Transaction::from_json_with(&v.to_string(), Decoding::Lenient).unwrap();
+        assert_eq!(lenient.verify(), Ok(()));
+
+        v["timestamp"] = json!("now");
+        let e = Transaction::from_json(&v.to_string()).unwrap_err();
+        assert_eq!(e.field(), Some("timestamp"));
+        assert_eq!(Transaction::from_json("{").unwrap_err().kind(), "json");
+    }
+
+    #[test]
+    fn test_signing_schemes() {
+        let (t0, sk0) = Transaction::debug_make_register_with(
+            format!("SN1337BIKE"),
+            SigningScheme::V1,
+            &mut Entropy::default(),
+        );
+        assert_eq!(t0.verify(), Ok(()));
+        let (t1, _) = Transaction::debug_make_transfer(&t0, &sk0);
+        assert_eq!(t1.get_scheme(), SigningScheme::V1);
+        assert_eq!(t1.verify_is_next(&t0), true);
+
+        // The version is part of the JSON and the signed bytes
+        let t = Transaction::from_json(&t1.to_json()).unwrap();
+        assert_eq!(t.verify(), Ok(()));
+        let mut legacy = t1.clone();
+        legacy.scheme = SigningScheme::Legacy;
+        assert!(legacy.verify().is_err());
+
+        // A transfer and a registration with the same legacy bytes
+        let key = |b: u8| PubKey::from_slice(&[b; 32]).unwrap();
+        let ts = |b: &[u8; 8]| u64::from_le_bytes(*b);
+        let transfer = |scheme| {
+            let id = format!("SN");
+            let (t, out) = (ts(b"12345678"), key(b'o'));
+            Transaction::from_details(id, t, Some(key(b'k')), out, Signature::default(), scheme)
+        };
+        let register = |scheme| {
+            let id = format!("SN12345678{}", "k".repeat(24));
+            let (t, out) = (ts(b"kkkkkkkk"), key(b'o'));
+            Transaction::from_details(id, t, None, out, Signature::default(), scheme)
+        };
+        let legacy = SigningScheme::Legacy;
+        assert_eq!(
+            transfer(legacy).content_to_u8(),
+            register(legacy).content_to_u8()
+        );
+        let v1 = SigningScheme::V1;
+        assert!(transfer(v1).content_to_u8() != register(v1).content_to_u8());
     }
 
     #[test]
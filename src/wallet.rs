@@ -0,0 +1,135 @@
+use crate::keys::PubKey;
+use crate::transaction::Transaction;
+use rust_sodium::crypto::sign::ed25519::SecretKey;
+use std::collections::HashMap;
+
+// ========================================================================== //
+
+/// Key that is owned by the client together with the transaction that made
+/// the client the owner
+#[derive(Clone, Debug)]
+pub struct WalletEntry {
+    /// Transaction with the public key as output
+    pub tx: Transaction,
+    /// Secret key for the output of the transaction
+    pub secret_key: SecretKey,
+}
+
+impl WalletEntry {
+    /// Returns the ID of the bike
+    pub fn get_id(&self) -> &String {
+        self.tx.get_id()
+    }
+
+    /// Returns the public key of the entry
+    pub fn get_public_key(&self) -> &PubKey {
+        self.tx.get_public_key_output()
+    }
+}
+
+// ========================================================================== //
+
+/// Keystore for the secret keys of all outputs that the client has created
+#[derive(Clone, Debug, Default)]
+pub struct Wallet {
+    /// Entries indexed by public key
+    keys: HashMap<PubKey, WalletEntry>,
+    /// Public keys for each bike id, in the order they were added
+    ids: HashMap<String, Vec<PubKey>>,
+}
+
+impl Wallet {
+    pub fn new() -> Wallet {
+        Wallet::default()
+    }
+
+    /// Add the secret key for the output of a transaction
+    pub fn insert(&mut self, tx: &Transaction, secret_key: SecretKey) {
+        let pk = *tx.get_public_key_output();
+        if !self.keys.contains_key(&pk) {
+            self.ids
+                .entry(tx.get_id().clone())
+                .or_insert_with(Vec::new)
+                .push(pk);
+        }
+        self.keys.insert(
+            pk,
+            WalletEntry {
+                tx: tx.clone(),
+                secret_key,
+            },
+        );
+    }
+
+    /// Returns the entry for a public key
+    pub fn get(&self, pk: &PubKey) -> Option<&WalletEntry> {
+        self.keys.get(pk)
+    }
+
+    /// Returns all entries for a bike, in the order they were added
+    pub fn get_by_id(&self, id: &str) -> Vec<&WalletEntry> {
+        match self.ids.get(id) {
+            Some(keys) => keys.iter().filter_map(|pk| self.keys.get(pk)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Returns the most recently added entry for a bike
+    pub fn latest(&self, id: &str) -> Option<&WalletEntry> {
+        self.ids
+            .get(id)
+            .and_then(|keys| keys.last())
+            .and_then(|pk| self.keys.get(pk))
+    }
+
+    /// Returns all entries, ordered by bike id and then in the order they
+    /// were added
+    pub fn entries(&self) -> Vec<&WalletEntry> {
+        let mut ids = self.ids();
+        ids.sort();
+        ids.into_iter().flat_map(|id| self.get_by_id(id)).collect()
+    }
+
+    /// Returns the ids of all bikes that the wallet has keys for
+    pub fn ids(&self) -> Vec<&String> {
+        self.ids.keys().collect()
+    }
+
+    /// Returns the number of keys in the wallet
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Returns whether the wallet is empty
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wallet_transfer() {
+        let mut wallet = Wallet::new();
+        let (t0, sk0) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        wallet.insert(&t0, sk0);
+
+        // Sign a transfer with the key from the wallet
+        let entry = wallet.latest("SN1337BIKE").unwrap();
+        let (t1, sk1) = Transaction::debug_make_transfer(&entry.tx, &entry.secret_key);
+        assert_eq!(t1.verify_is_next(&t0), true);
+        wallet.insert(&t1, sk1);
+
+        assert_eq!(wallet.len(), 2);
+        assert_eq!(wallet.get_by_id("SN1337BIKE").len(), 2);
+        assert_eq!(
+            wallet.latest("SN1337BIKE").unwrap().tx.get_signature(),
+            t1.get_signature()
+        );
+        assert!(wallet.get(t0.get_public_key_output()).is_some());
+        assert!(wallet.latest("SN0").is_none());
+    }
+}
@@ -0,0 +1,156 @@
+use crate::transaction::{Decoding, Transaction, TxError};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+// ========================================================================== //
+
+/// Magic bytes at the start of a binary transaction
+pub const WIRE_MAGIC: [u8; 2] = *b"BT";
+
+/// Version of the binary encoding, which follows the magic bytes. Version 2
+/// added the signing scheme of the transaction.
+pub const WIRE_VERSION: u8 = 2;
+
+// ========================================================================== //
+
+/// Encodings of a transaction on the wire
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// JSON object with base64 keys and signature
+    Json,
+    /// Version header followed by the bincode encoding of the transaction
+    Binary,
+}
+
+/// All formats
+pub const FORMATS: [Format; 2] = [Format::Json, Format::Binary];
+
+impl Format {
+    /// Returns the name of the format
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Binary => "binary",
+        }
+    }
+
+    /// Returns a short description of the format
+    pub fn description(self) -> &'static str {
+        match self {
+            Format::Json => "JSON",
+            Format::Binary => "Binary",
+        }
+    }
+
+    /// Returns the content type of a request body in the format
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Binary => "application/octet-stream",
+        }
+    }
+
+    /// Returns the format of a request body with the specified content type.
+    /// Parameters like the charset are ignored.
+    pub fn from_content_type(content_type: &str) -> Option<Format> {
+        let mime = content_type.split(';').next().unwrap_or("").trim();
+        FORMATS
+            .iter()
+            .find(|f| f.content_type().eq_ignore_ascii_case(mime))
+            .cloned()
+    }
+}
+
+impl Default for Format {
+    fn default() -> Format {
+        Format::Json
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match FORMATS.iter().find(|f| f.name() == s) {
+            Some(format) => Ok(*format),
+            None => Err(format!("Unknown format '{}'", s)),
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// ========================================================================== //
+
+/// Encode a transaction in a format
+pub fn encode(tx: &Transaction, format: Format) -> Vec<u8> {
+    match format {
+        Format::Json => tx.to_json().into_bytes(),
+        Format::Binary => {
+            let mut buf = WIRE_MAGIC.to_vec();
+            buf.push(WIRE_VERSION);
+            buf.extend(bincode::serialize(tx).expect("Failed to convert to binary"));
+            buf
+        }
+    }
+}
+
+/// Decode a transaction in a format. The decoding mode applies to JSON.
+pub fn decode(body: &[u8], format: Format, decoding: Decoding) -> Result<Transaction, TxError> {
+    match format {
+        Format::Json => match std::str::from_utf8(body) {
+            Ok(json) => Transaction::from_json_with(json, decoding),
+            Err(e) => Err(TxError::Json(e.to_string())),
+        },
+        Format::Binary => {
+            if body.len() < WIRE_MAGIC.len() + 1 || body[..WIRE_MAGIC.len()] != WIRE_MAGIC {
+                return Err(TxError::Binary(format!("missing header")));
+            }
+            let version = body[WIRE_MAGIC.len()];
+            if version != WIRE_VERSION {
+                return Err(TxError::Binary(format!("unsupported version {}", version)));
+            }
+            match bincode::deserialize(&body[WIRE_MAGIC.len() + 1..]) {
+                Ok(tx) => Ok(tx),
+                Err(e) => Err(TxError::Binary(e.to_string())),
+            }
+        }
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_binary_format() {
+        let (tx, _) = Transaction::debug_make_register(format!("SN1337BIKE"));
+        let body = encode(&tx, Format::Binary);
+        assert_eq!(body[..3], [b'B', b'T', WIRE_VERSION]);
+        assert!(body.len() < encode(&tx, Format::Json).len());
+        let decoded = decode(&body, Format::Binary, Decoding::Normal).unwrap();
+        assert_eq!(decoded.to_json(), tx.to_json());
+        assert_eq!(decoded.verify(), Ok(()));
+
+        let mut bad = body.clone();
+        bad[2] = WIRE_VERSION + 1;
+        assert_eq!(
+            decode(&bad, Format::Binary, Decoding::Normal)
+                .unwrap_err()
+                .kind(),
+            "binary"
+        );
+        assert!(decode(&body[..body.len() - 1], Format::Binary, Decoding::Normal).is_err());
+        assert_eq!(
+            Format::from_content_type("application/json; charset=utf-8"),
+            Some(Format::Json)
+        );
+    }
+}
@@ -0,0 +1,634 @@
+use crate::attack::{self, Attack, AttackWorkload};
+use crate::entropy::Entropy;
+use crate::expect::Verdict;
+use crate::transaction::{SigningScheme, Transaction};
+use crate::wire::{self, Format};
+use rand::Rng;
+use rust_sodium::crypto::sign::ed25519::SecretKey;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::time::Instant;
+
+// ========================================================================== //
+
+/// Default number of bikes that are registered in an ownership-chain run
+pub const DEFAULT_BIKES: u32 = 10;
+
+/// Label of jobs that register a bike
+pub const LABEL_REGISTER: &str = "register";
+
+/// Label of jobs that transfer a bike
+pub const LABEL_TRANSFER: &str = "transfer";
+
+/// Label of transactions that the user sends manually
+pub const LABEL_MANUAL: &str = "manual";
+
+/// Number of names that are generated for a new bike before giving up on
+/// finding one that is not in use
+const MAX_NAME_ATTEMPTS: u32 = 1000;
+
+// ========================================================================== //
+
+/// A single transaction that is to be sent by the load engine
+#[derive(Clone)]
+pub struct Job {
+    /// Kind of transaction, shown in the history
+    pub label: String,
+    /// Transaction to send
+    pub tx: Transaction,
+    /// Secret key for the output of the transaction, if the client created it
+    pub secret_key: Option<SecretKey>,
+    /// Body to send instead of the JSON of the transaction, for payloads that
+    /// a Transaction cannot represent
+    pub body: Option<String>,
+    /// Verdict that the node is expected to give, if it is known
+    pub expected: Option<Verdict>,
+    /// Time that the rate profile scheduled the job to be sent at, or None if
+    /// it is sent as soon as a worker is available
+    pub scheduled: Option<Instant>,
+}
+
+impl Job {
+    pub fn new(label: &str, tx: Transaction, secret_key: Option<SecretKey>) -> Job {
+        Job {
+            label: String::from(label),
+            tx,
+            secret_key,
+            body: None,
+            expected: None,
+            scheduled: None,
+        }
+    }
+
+    /// Returns the body of the request for the job
+    pub fn body(&self) -> String {
+        match self.body {
+            Some(ref body) => body.clone(),
+            None => self.tx.to_json(),
+        }
+    }
+
+    /// Returns the body of the request for the job in a format, with the
+    /// format that was used. A job with a custom body is always sent as JSON.
+    pub fn encode(&self, format: Format) -> (Vec<u8>, Format) {
+        match self.body {
+            Some(ref body) => (body.clone().into_bytes(), Format::Json),
+            None => (wire::encode(&self.tx, format), format),
+        }
+    }
+}
+
+/// Next job of a workload
+pub enum NextJob {
+    /// A job is ready to be sent
+    Ready(Job),
+    /// No job is ready until a job that is in flight has completed
+    Pending,
+    /// The workload is exhausted
+    Done,
+}
+
+// ========================================================================== //
+
+/// Trait for sources of transactions that drive a load run
+pub trait Workload: Send {
+    /// Returns the next job to send
+    fn next_job(&mut self) -> NextJob;
+
+    /// Called by the load engine once a job has been sent. 'accepted' is true
+    /// if the node accepted the transaction.
+    fn on_result(&mut self, _job: &Job, _accepted: bool) {}
+}
+
+// ========================================================================== //
+
+/// Kinds of workloads that can be selected for a run
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    /// Register new bikes
+    Register,
+    /// Register bikes and transfer them between owners
+    Chain,
+    /// Send transactions that the node must reject
+    Attack(Attack),
+}
+
+impl Mode {
+    /// Create a workload of this kind that generates 'count' transactions,
+    /// signed with 'scheme', with names, keys and timestamps from 'entropy'.
+    /// For the chain mode, 'bikes' of those are registrations. For attacks,
+    /// 'count' is the number of attacks.
+    pub fn make_workload(
+        self,
+        names: Vec<String>,
+        count: u32,
+        bikes: u32,
+        scheme: SigningScheme,
+        entropy: Entropy,
+    ) -> Box<dyn Workload> {
+        match self {
+            Mode::Register => Box::new(RegisterWorkload::new(names, count, scheme, entropy)),
+            Mode::Chain => Box::new(ChainWorkload::new(names, bikes, count, scheme, entropy)),
+            Mode::Attack(attack) => {
+                Box::new(AttackWorkload::new(names, attack, count, scheme, entropy))
+            }
+        }
+    }
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Mode, String> {
+        match s {
+            "register" => Ok(Mode::Register),
+            "chain" => Ok(Mode::Chain),
+            _ => match s.parse::<Attack>() {
+                Ok(attack) => Ok(Mode::Attack(attack)),
+                Err(_) => Err(format!("Unknown mode '{}'", s)),
+            },
+        }
+    }
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Mode::Register => write!(f, "register"),
+            Mode::Chain => write!(f, "chain"),
+            Mode::Attack(attack) => write!(f, "{}", attack),
+        }
+    }
+}
+
+// ========================================================================== //
+
+/// Workload that registers new bikes with random names
+pub struct RegisterWorkload {
+    /// List of names
+    names: Vec<String>,
+    /// Number of transactions left to generate
+    remaining: u32,
+    /// Signing scheme of the transactions
+    scheme: SigningScheme,
+    /// Source of the names, keys and timestamps
+    entropy: Entropy,
+}
+
+impl RegisterWorkload {
+    pub fn new(
+        names: Vec<String>,
+        count: u32,
+        scheme: SigningScheme,
+        entropy: Entropy,
+    ) -> RegisterWorkload {
+        RegisterWorkload {
+            names,
+            remaining: count,
+            scheme,
+            entropy,
+        }
+    }
+}
+
+impl Workload for RegisterWorkload {
+    fn next_job(&mut self) -> NextJob {
+        if self.remaining == 0 {
+            return NextJob::Done;
+        }
+        self.remaining -= 1;
+        let id = self.entropy.gen_name(&self.names);
+        let (tx, sk) = Transaction::debug_make_register_with(id, self.scheme, &mut self.entropy);
+        NextJob::Ready(Job::new(LABEL_REGISTER, tx, Some(sk)))
+    }
+}
+
+// ========================================================================== //
+
+/// Bike in an ownership-chain simulation
+struct Bike {
+    /// Last transaction of the chain that the node accepted
+    head: Option<Transaction>,
+    /// Secret key of the current owner
+    secret_key: SecretKey,
+    /// Whether a transaction for the bike is in flight
+    busy: bool,
+}
+
+/// Ownership chains of the bikes that a workload has registered. At most one
+/// transaction per bike is in flight, so that the node receives every chain in
+/// order. Bikes are transferred strictly in turn, so the transactions only
+/// depend on the entropy and the verdicts of the node, and not on the order in
+/// which the results arrive.
+struct Chains {
+    /// List of names
+    names: Vec<String>,
+    /// Bikes indexed by id
+    bikes: HashMap<String, Bike>,
+    /// Ids of the bikes in the order they were registered. A bike whose
+    /// registration was rejected is removed when it is its turn.
+    order: Vec<String>,
+    /// Index in 'order' of the next bike to transfer
+    next: usize,
+    /// Signing scheme of the registrations, which the transfers inherit
+    scheme: SigningScheme,
+    /// Source of the names, keys and timestamps
+    entropy: Entropy,
+}
+
+impl Chains {
+    fn new(names: Vec<String>, scheme: SigningScheme, entropy: Entropy) -> Chains {
+        Chains {
+            names,
+            bikes: HashMap::new(),
+            order: Vec::new(),
+            next: 0,
+            scheme,
+            entropy,
+        }
+    }
+
+    /// Register a new bike with a name that is not in use. Returns None if no
+    /// free name was generated in MAX_NAME_ATTEMPTS attempts, which happens
+    /// once nearly every name is in use.
+    fn register(&mut self) -> Option<Job> {
+        let mut attempts = 1;
+        let mut id = self.entropy.gen_name(&self.names);
+        while self.bikes.contains_key(&id) {
+            if attempts == MAX_NAME_ATTEMPTS {
+                return None;
+            }
+            attempts += 1;
+            id = self.entropy.gen_name(&self.names);
+        }
+        let (tx, sk) =
+            Transaction::debug_make_register_with(id.clone(), self.scheme, &mut self.entropy);
+        self.bikes.insert(
+            id.clone(),
+            Bike {
+                head: None,
+                secret_key: sk.clone(),
+                busy: true,
+            },
+        );
+        self.order.push(id);
+        Some(Job::new(LABEL_REGISTER, tx, Some(sk)))
+    }
+
+    /// Transfer the bike whose turn it is. Each transfer is signed by the
+    /// previous owner and hands the bike to a newly generated owner key.
+    /// Returns None while the transaction before it is in flight, or if there
+    /// are no bikes.
+    fn transfer(&mut self) -> Option<Job> {
+        while !self.order.is_empty() {
+            let idx = self.next % self.order.len();
+            let bike = match self.bikes.get_mut(&self.order[idx]) {
+                Some(bike) => bike,
+                None => {
+                    self.order.remove(idx);
+                    continue;
+                }
+            };
+            if bike.busy {
+                return None;
+            }
+            let head = bike.head.as_ref()?;
+            let (tx, sk) =
+                Transaction::debug_make_transfer_with(head, &bike.secret_key, &mut self.entropy);
+            bike.busy = true;
+            self.next = idx + 1;
+            return Some(Job::new(LABEL_TRANSFER, tx, Some(sk)));
+        }
+        None
+    }
+
+    /// Update the chain of a bike once a job has been sent
+    fn complete(&mut self, job: &Job, accepted: bool) {
+        let id = job.tx.get_id();
+        let bike = match self.bikes.get_mut(id) {
+            Some(bike) => bike,
+            None => return,
+        };
+        bike.busy = false;
+        if !accepted {
+            // A bike that could not be registered cannot be transferred
+            if bike.head.is_none() {
+                self.bikes.remove(id);
+            }
+            return;
+        }
+        if let Some(ref sk) = job.secret_key {
+            bike.head = Some(job.tx.clone());
+            bike.secret_key = sk.clone();
+        }
+    }
+
+    /// Returns whether there are no bikes
+    fn is_empty(&self) -> bool {
+        self.bikes.is_empty()
+    }
+}
+
+// ========================================================================== //
+
+/// Workload that registers a number of bikes and then repeatedly transfers
+/// them between simulated owners
+pub struct ChainWorkload {
+    /// Chains of the registered bikes
+    chains: Chains,
+    /// Number of bikes left to register
+    registers: u32,
+    /// Number of transactions left to generate
+    remaining: u32,
+}
+
+impl ChainWorkload {
+    /// Create a workload that registers 'bikes' bikes and generates 'count'
+    /// transactions in total
+    pub fn new(
+        names: Vec<String>,
+        bikes: u32,
+        count: u32,
+        scheme: SigningScheme,
+        entropy: Entropy,
+    ) -> ChainWorkload {
+        ChainWorkload {
+            chains: Chains::new(names, scheme, entropy),
+            registers: bikes.min(count),
+            remaining: count,
+        }
+    }
+}
+
+impl Workload for ChainWorkload {
+    fn next_job(&mut self) -> NextJob {
+        if self.remaining == 0 {
+            return NextJob::Done;
+        }
+        if self.registers > 0 {
+            self.registers -= 1;
+            self.remaining -= 1;
+            return match self.chains.register() {
+                Some(job) => NextJob::Ready(job),
+                None => NextJob::Done,
+            };
+        }
+        match self.chains.transfer() {
+            Some(job) => {
+                self.remaining -= 1;
+                NextJob::Ready(job)
+            }
+            None if self.chains.is_empty() => NextJob::Done,
+            None => NextJob::Pending,
+        }
+    }
+
+    fn on_result(&mut self, job: &Job, accepted: bool) {
+        self.chains.complete(job, accepted);
+    }
+}
+
+// ========================================================================== //
+
+/// Relative weights of the kinds of transactions in a mixed workload
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mix {
+    /// Registrations of new bikes
+    pub register: f64,
+    /// Transfers of registered bikes
+    pub transfer: f64,
+    /// Transactions that the node must reject
+    pub invalid: f64,
+}
+
+impl Default for Mix {
+    fn default() -> Mix {
+        Mix {
+            register: 1.0,
+            transfer: 0.0,
+            invalid: 0.0,
+        }
+    }
+}
+
+/// Workload that mixes registrations, transfers and invalid transactions with
+/// the weights of a mix. A transfer waits for the bike whose turn it is, and
+/// is replaced by a registration while there are no bikes.
+pub struct MixedWorkload {
+    /// Chains of the registered bikes
+    chains: Chains,
+    /// Weights of the kinds of transactions
+    mix: Mix,
+    /// Pick of a transfer that waits for its bike
+    held: Option<f64>,
+    /// Number of transactions left to generate, or None for no limit
+    remaining: Option<u32>,
+}
+
+impl MixedWorkload {
+    /// Create a mixed workload. The kind of each transaction is picked with
+    /// the generator of 'entropy', so a seeded source gives the same sequence
+    /// of kinds in every run.
+    pub fn new(
+        names: Vec<String>,
+        mix: Mix,
+        count: Option<u32>,
+        scheme: SigningScheme,
+        entropy: Entropy,
+    ) -> MixedWorkload {
+        MixedWorkload {
+            chains: Chains::new(names, scheme, entropy),
+            mix,
+            held: None,
+            remaining: count,
+        }
+    }
+
+    /// Make a registration that the node must reject, since the signature
+    /// does not match the content
+    fn make_invalid(&mut self) -> Job {
+        let chains = &mut self.chains;
+        let id = chains.entropy.gen_name(&chains.names);
+        attack::make_bad_signature(id, chains.scheme, &mut chains.entropy)
+    }
+}
+
+impl Workload for MixedWorkload {
+    fn next_job(&mut self) -> NextJob {
+        if self.remaining == Some(0) {
+            return NextJob::Done;
+        }
+        let total = self.mix.register + self.mix.transfer + self.mix.invalid;
+        let pick = match self.held.take() {
+            Some(pick) => pick,
+            None => self.chains.entropy.rng().gen::<f64>() * total,
+        };
+        let job = if pick < self.mix.register {
+            self.chains.register()
+        } else if pick < self.mix.register + self.mix.transfer {
+            match self.chains.transfer() {
+                Some(job) => Some(job),
+                None if self.chains.is_empty() => self.chains.register(),
+                None => {
+                    self.held = Some(pick);
+                    return NextJob::Pending;
+                }
+            }
+        } else {
+            Some(self.make_invalid())
+        };
+        let job = match job {
+            Some(job) => job,
+            None => return NextJob::Done,
+        };
+        self.remaining = self.remaining.map(|n| n - 1);
+        NextJob::Ready(job)
+    }
+
+    fn on_result(&mut self, job: &Job, accepted: bool) {
+        self.chains.complete(job, accepted);
+    }
+}
+
+// Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::names;
+
+    fn take(w: &mut dyn Workload) -> Job {
+        match w.next_job() {
+            NextJob::Ready(job) => job,
+            _ => panic!("Expected a job"),
+        }
+    }
+
+    #[test]
+    fn test_chain_workload() {
+        let names = vec![String::from("Bike")];
+        let mut w = ChainWorkload::new(names, 1, 4, SigningScheme::V1, Entropy::default());
+
+        // Transfers wait for the registration to complete
+        let t0 = take(&mut w);
+        assert!(!t0.tx.has_input());
+        assert!(match w.next_job() {
+            NextJob::Pending => true,
+            _ => false,
+        });
+        w.on_result(&t0, true);
+
+        // Each transfer continues the chain of the previous one
+        let t1 = take(&mut w);
+        assert_eq!(t1.tx.verify_is_next(&t0.tx), true);
+        w.on_result(&t1, true);
+        let t2 = take(&mut w);
+        assert_eq!(t2.tx.verify_is_next(&t1.tx), true);
+
+        // A rejected transfer is retried from the last accepted head
+        w.on_result(&t2, false);
+        let t3 = take(&mut w);
+        assert_eq!(t3.tx.verify_is_next(&t1.tx), true);
+        assert!(match w.next_job() {
+            NextJob::Done => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_names_run_out() {
+        // Registrations stop once no free name is found
+        let names = vec![String::from("Bike")];
+        let capacity = names::capacity(&names) as u32;
+        let entropy = Entropy::new(Some(3), Some(1_600_000_000));
+        let mut w = ChainWorkload::new(
+            names,
+            capacity + 1,
+            capacity + 2,
+            SigningScheme::V1,
+            entropy,
+        );
+        let mut registered = 0;
+        while let NextJob::Ready(job) = w.next_job() {
+            assert!(!job.tx.has_input());
+            registered += 1;
+        }
+        assert!(registered > 0 && registered <= capacity);
+    }
+
+    #[test]
+    fn test_seeded_workload() {
+        let run = |seed| {
+            let names = vec![String::from("Anna"), String::from("Bertil")];
+            let entropy = Entropy::new(Some(seed), Some(1_600_000_000));
+            let mut w = ChainWorkload::new(names, 2, 6, SigningScheme::V1, entropy);
+            let mut bodies = Vec::new();
+            while let NextJob::Ready(job) = w.next_job() {
+                w.on_result(&job, true);
+                bodies.push(job.body());
+            }
+            bodies
+        };
+
+        // The same seed and clock give byte-identical transactions
+        let bodies = run(42);
+        assert_eq!(bodies.len(), 6);
+        assert_eq!(run(42), bodies);
+        assert!(run(43) != bodies);
+    }
+
+    #[test]
+    fn test_reordered_results() {
+        // Two jobs are in flight, and either the oldest or the newest one
+        // completes first
+        let run = |mix: Option<Mix>, newest_first: bool| {
+            let names = vec![String::from("Anna"), String::from("Bertil")];
+            let entropy = Entropy::new(Some(42), Some(1_600_000_000));
+            let mut w: Box<dyn Workload> = match mix {
+                Some(mix) => Box::new(MixedWorkload::new(
+                    names,
+                    mix,
+                    Some(12),
+                    SigningScheme::V1,
+                    entropy,
+                )),
+                None => Box::new(ChainWorkload::new(names, 3, 12, SigningScheme::V1, entropy)),
+            };
+            let mut in_flight = Vec::new();
+            let mut bodies = Vec::new();
+            loop {
+                match w.next_job() {
+                    NextJob::Ready(job) => {
+                        bodies.push(job.body());
+                        in_flight.push(job);
+                        if in_flight.len() < 2 {
+                            continue;
+                        }
+                    }
+                    NextJob::Pending => {}
+                    NextJob::Done => break,
+                }
+                let job = match newest_first {
+                    true => in_flight.pop().unwrap(),
+                    false => in_flight.remove(0),
+                };
+                w.on_result(&job, true);
+            }
+            bodies
+        };
+
+        let bodies = run(None, false);
+        assert_eq!(bodies.len(), 12);
+        assert_eq!(run(None, true), bodies);
+        let mix = Mix {
+            register: 1.0,
+            transfer: 3.0,
+            invalid: 0.5,
+        };
+        let bodies = run(Some(mix.clone()), false);
+        assert_eq!(bodies.len(), 12);
+        assert_eq!(run(Some(mix), true), bodies);
+    }
+}